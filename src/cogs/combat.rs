@@ -0,0 +1,114 @@
+// Fighting other entities.
+// ----------------------------
+use specs::prelude::*;
+
+use crate::cogs::Cog;
+use crate::game::{Output, RunState};
+use crate::game::commands::{find_target, Args, Command, Context};
+use crate::game::components;
+use crate::game::components::helpers::{get_entity_room_id, get_room_entities_as_bitset};
+
+
+pub struct Combat;
+
+impl Cog for Combat {
+    fn name(&self) -> &'static str {
+        "combat"
+    }
+
+    fn commands(&self) -> Vec<Box<dyn Command>> {
+        vec![Box::new(Attack), Box::new(Status)]
+    }
+}
+
+struct Attack;
+
+impl Command for Attack {
+    fn name(&self) -> &'static str {
+        "attack"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["kill", "hit"]
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["attack <target>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Attack something in the room"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["attack robot pirate", "kill 2.robot"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let x = args.get("target").unwrap();
+        let mut output = ctx.world.write_resource::<Output>();
+        let room_id = get_entity_room_id(ctx.player, ctx.world)
+            .expect("Expected player to be in a room");
+        let room_entities = get_room_entities_as_bitset(room_id, ctx.world);
+        let entities = ctx.world.entities();
+        let ds = ctx.world.read_storage::<components::Description>();
+        let healths = ctx.world.read_storage::<components::Health>();
+        // Only things which can still be hurt are worth fighting
+        let candidates = (&entities, &ds, &healths, &room_entities).join()
+            .filter(|(e, _, health, _)| *e != ctx.player && health.current > 0)
+            .map(|(e, d, _, _)| (e, d));
+        let not_found = format!("There's nothing like \"{}\" to fight here", x);
+        if let Some(target) = find_target(ctx.player, x, candidates, &mut output, not_found) {
+            ctx.world.write_storage::<components::WantsToAttack>()
+                .insert(ctx.player, components::WantsToAttack { target })
+                .expect("Unable to insert WantsToAttack");
+            RunState::PlayerTurn
+        } else {
+            RunState::AwaitingInput
+        }
+    }
+}
+
+struct Status;
+
+impl Command for Status {
+    fn name(&self) -> &'static str {
+        "status"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["hp"]
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["status"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Show your health and stats"
+    }
+
+    fn run(&self, ctx: &Context, _args: &Args) -> RunState {
+        let healths = ctx.world.read_storage::<components::Health>();
+        let stats = ctx.world.read_storage::<components::CombatStats>();
+        let equipped = ctx.world.read_storage::<components::Equipped>();
+        let equippables = ctx.world.read_storage::<components::Equippable>();
+        let mut lines = Vec::new();
+        if let Some(health) = healths.get(ctx.player) {
+            lines.push(format!("Health: {}/{}", health.current, health.max));
+        }
+        if stats.contains(ctx.player) {
+            // Counting whatever the player has equipped
+            let stats = components::effective_stats(ctx.player, &stats, &equipped, &equippables);
+            lines.push(format!("Attack: {}, Defense: {}", stats.attack, stats.defense));
+        }
+        if let Some(stealth) = ctx.world.read_storage::<components::Stealth>().get(ctx.player) {
+            lines.push(format!("Stealth: {}", stealth.skill));
+        }
+        if lines.is_empty() {
+            lines.push("You're in no shape to fight.".to_string());
+        }
+        ctx.world.write_resource::<Output>().send(ctx.player, lines.join("\n"));
+        RunState::AwaitingInput
+    }
+}
@@ -0,0 +1,267 @@
+// Talking, to other players and to NPCs.
+// ----------------------------
+use specs::prelude::*;
+
+use crate::cogs::Cog;
+use crate::game::{Output, RunState};
+use crate::game::commands::{find_target, Args, Command, Context};
+use crate::game::components;
+use crate::game::conversation;
+use crate::game::components::helpers::{
+    get_entity_room_id,
+    get_room_entities_as_bitset,
+    get_room_players_as_descriptions,
+};
+use crate::game::targeting::{self, Target};
+
+
+pub struct Dialogue;
+
+impl Cog for Dialogue {
+    fn name(&self) -> &'static str {
+        "dialogue"
+    }
+
+    fn commands(&self) -> Vec<Box<dyn Command>> {
+        vec![Box::new(Say), Box::new(Tell), Box::new(Ask), Box::new(Talk)]
+    }
+}
+
+struct Say;
+
+impl Command for Say {
+    fn name(&self) -> &'static str {
+        "say"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["say <message>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Say something to everyone in the room"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["say hello everyone!"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let message = args.get("message").unwrap();
+        let mut output = ctx.world.write_resource::<Output>();
+        let speaker = speaker_name(ctx);
+        output.send(ctx.player, format!("You say, \"{}\"", message));
+        for listener in others_in_room(ctx) {
+            output.send(listener, format!("{} says, \"{}\"", speaker, message));
+        }
+
+        // Greetings are returned by anyone in the room with something to say back
+        if components::is_greeting(message) {
+            let room_id = get_entity_room_id(ctx.player, ctx.world)
+                .expect("Expected player to be in a room");
+            let room_entities = get_room_entities_as_bitset(room_id, ctx.world);
+            let dialogues = ctx.world.read_storage::<components::Dialogue>();
+            let npcs: Vec<Entity> = (&ctx.world.entities(), &dialogues, &room_entities).join()
+                .filter(|(_, dialogue, _)| dialogue.greeting.is_some())
+                .map(|(e, _, _)| e)
+                .collect();
+            for npc in npcs {
+                reply(ctx, npc, message, &mut output);
+            }
+        }
+        RunState::AwaitingInput
+    }
+}
+
+struct Tell;
+
+impl Command for Tell {
+    fn name(&self) -> &'static str {
+        "tell"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["tell <target>, <message>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Say something to someone in the room"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["tell receptionist, hello!", "tell 2.robot, go away!", "tell bob, follow me"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let message = args.get("message").unwrap();
+        let x = args.get("target").unwrap();
+        let mut output = ctx.world.write_resource::<Output>();
+        let speaker = speaker_name(ctx);
+
+        // Other players are told directly, by name
+        let players = get_room_players_as_descriptions(
+            get_entity_room_id(ctx.player, ctx.world).expect("Expected player to be in a room"),
+            ctx.player,
+            ctx.world,
+        );
+        let player = targeting::resolve(x, players.iter().map(|(e, d)| (*e, d)));
+        if let Target::Found(player) = player {
+            let name = &players.iter().find(|(e, _)| *e == player).unwrap().1.glance;
+            output.send(ctx.player, format!("You tell {}, \"{}\"", name, message));
+            output.send(player, format!("{} tells you, \"{}\"", speaker, message));
+            for listener in others_in_room(ctx).into_iter().filter(|listener| *listener != player) {
+                output.send(listener, format!("{} tells {}, \"{}\"", speaker, name, message));
+            }
+            return RunState::AwaitingInput
+        }
+
+        let npc = match find_npc(ctx, x, &mut output) {
+            Some(npc) => npc,
+            None => return RunState::AwaitingInput,
+        };
+        let glance = glance(ctx, npc);
+        output.send(ctx.player, format!("You tell the {}, \"{}\"", glance, message));
+        for listener in others_in_room(ctx) {
+            output.send(listener, format!("{} tells the {}, \"{}\"", speaker, glance, message));
+        }
+        reply(ctx, npc, message, &mut output);
+        RunState::AwaitingInput
+    }
+}
+
+struct Ask;
+
+impl Command for Ask {
+    fn name(&self) -> &'static str {
+        "ask"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["ask <target> about <topic>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Ask someone in the room about something"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["ask receptionist about the storage car"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let topic = args.get("topic").unwrap();
+        let mut output = ctx.world.write_resource::<Output>();
+        let npc = match find_npc(ctx, args.get("target").unwrap(), &mut output) {
+            Some(npc) => npc,
+            None => return RunState::AwaitingInput,
+        };
+        let glance = glance(ctx, npc);
+        let speaker = speaker_name(ctx);
+        output.send(ctx.player, format!("You ask the {} about {}.", glance, topic));
+        for listener in others_in_room(ctx) {
+            output.send(listener, format!("{} asks the {} about {}.", speaker, glance, topic));
+        }
+        reply(ctx, npc, topic, &mut output);
+        RunState::AwaitingInput
+    }
+}
+
+struct Talk;
+
+impl Command for Talk {
+    fn name(&self) -> &'static str {
+        "talk"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["talk to <target>", "talk <target>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Start a conversation with someone in the room"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["talk to receptionist"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let npc = {
+            let mut output = ctx.world.write_resource::<Output>();
+            match find_npc(ctx, args.get("target").unwrap(), &mut output) {
+                Some(npc) => npc,
+                None => return RunState::AwaitingInput,
+            }
+        };
+        let converses = ctx.world.read_storage::<components::Dialogue>()
+            .get(npc)
+            .is_some_and(components::Dialogue::converses);
+        if converses {
+            conversation::start(ctx.world, ctx.player, npc);
+        } else {
+            // Without anything to talk about, NPCs just return the greeting
+            reply(ctx, npc, "hello", &mut ctx.world.write_resource::<Output>());
+        }
+        RunState::AwaitingInput
+    }
+}
+
+/// Have an NPC answer something said to it, for the whole room to hear. NPCs with nothing to say
+/// still let the player know they were heard.
+fn reply(ctx: &Context, npc: Entity, message: &str, output: &mut Output) {
+    let dialogues = ctx.world.read_storage::<components::Dialogue>();
+    let glance = glance(ctx, npc);
+    match dialogues.get(npc).and_then(|dialogue| dialogue.respond(message)) {
+        Some(response) => {
+            let line = format!("The {} says, \"{}\"", glance, response);
+            output.send(ctx.player, line.clone());
+            for listener in others_in_room(ctx) {
+                output.send(listener, line.clone());
+            }
+        }
+        None if components::is_greeting(message) => {
+            output.send(ctx.player, format!("The {} nods at you.", glance));
+        }
+        None => {
+            output.send(ctx.player, format!("The {} doesn't seem to understand.", glance));
+        }
+    }
+}
+
+/// Work out which NPC in the room the player is talking to.
+fn find_npc(ctx: &Context, x: &str, output: &mut Output) -> Option<Entity> {
+    let room_id = get_entity_room_id(ctx.player, ctx.world)
+        .expect("Expected player to be in a room");
+    let room_entities = get_room_entities_as_bitset(room_id, ctx.world);
+    let entities = ctx.world.entities();
+    let ds = ctx.world.read_storage::<components::Description>();
+    let npcs = ctx.world.read_storage::<components::Npc>();
+    let candidates = (&entities, &ds, &npcs, &room_entities).join().map(|(e, d, _, _)| (e, d));
+    let not_found = format!("There's nobody like \"{}\" here to talk to", x);
+    find_target(ctx.player, x, candidates, output, not_found)
+}
+
+fn glance(ctx: &Context, entity: Entity) -> String {
+    ctx.world.read_storage::<components::Description>()
+        .get(entity)
+        .map_or("someone".to_string(), |d| d.glance.clone())
+}
+
+/// What other players call the player speaking.
+fn speaker_name(ctx: &Context) -> String {
+    ctx.world.read_storage::<components::DisplayName>()
+        .get(ctx.player)
+        .map_or("Someone".to_string(), |d| d.name.clone())
+}
+
+/// Every other player in the speaking player's room.
+fn others_in_room(ctx: &Context) -> Vec<Entity> {
+    let room_id = get_entity_room_id(ctx.player, ctx.world)
+        .expect("Expected player to be in a room");
+    let players = ctx.world.read_storage::<components::Player>();
+    let inrooms = ctx.world.read_storage::<components::InRoom>();
+    (&ctx.world.entities(), &players, &inrooms).join()
+        .filter(|(e, _, inroom)| *e != ctx.player && inroom.room == room_id)
+        .map(|(e, _, _)| e)
+        .collect()
+}
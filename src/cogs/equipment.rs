@@ -0,0 +1,214 @@
+// Wearing and wielding things.
+// ----------------------------
+use specs::prelude::*;
+
+use crate::cogs::Cog;
+use crate::game::{Message, Output, RunState};
+use crate::game::commands::{find_target, Args, Command, Context};
+use crate::game::components;
+use crate::game::components::helpers::get_entities_in_storage_as_bitset;
+
+
+pub struct Equipment;
+
+impl Cog for Equipment {
+    fn name(&self) -> &'static str {
+        "equipment"
+    }
+
+    fn commands(&self) -> Vec<Box<dyn Command>> {
+        vec![Box::new(Wear), Box::new(Wield), Box::new(Remove), Box::new(ListEquipment)]
+    }
+}
+
+struct Wear;
+
+impl Command for Wear {
+    fn name(&self) -> &'static str {
+        "wear"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["wear <item>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Put on something you carry"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["wear hat"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        equip(ctx, args.get("item").unwrap(), false)
+    }
+}
+
+struct Wield;
+
+impl Command for Wield {
+    fn name(&self) -> &'static str {
+        "wield"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["wield <item>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Take up something you carry in hand"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["wield cutlass"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        equip(ctx, args.get("item").unwrap(), true)
+    }
+}
+
+/// Put on or wield something from the player's inventory, as long as that's how it's used.
+fn equip(ctx: &Context, x: &str, wield: bool) -> RunState {
+    let mut output = ctx.world.write_resource::<Output>();
+    let inventory = get_entities_in_storage_as_bitset(ctx.player, ctx.world)
+        .expect("Expected player to have an inventory!");
+    let entities = ctx.world.entities();
+    let ds = ctx.world.read_storage::<components::Description>();
+    let candidates = (&entities, &ds, &inventory).join().map(|(e, d, _)| (e, d));
+    let not_found = format!("Nothing in your inventory like \"{}\"", x);
+    let item = match find_target(ctx.player, x, candidates, &mut output, not_found) {
+        Some(item) => item,
+        None => return RunState::AwaitingInput,
+    };
+
+    let glance = &ds.get(item).unwrap().glance;
+    let (verb, other) = if wield { ("wield", "wear") } else { ("wear", "wield") };
+    match ctx.world.read_storage::<components::Equippable>().get(item) {
+        Some(equippable) if equippable.slot.is_held() == wield => {}
+        Some(_) => {
+            let error = format!("You can't {} the {}. Try \"{} {}\"", verb, glance, other, x);
+            output.send(ctx.player, Message::error(error));
+            return RunState::AwaitingInput
+        }
+        None => {
+            output.send(ctx.player, Message::error(format!("You can't {} the {}.", verb, glance)));
+            return RunState::AwaitingInput
+        }
+    }
+
+    ctx.world.write_storage::<components::ApplyEquipmentChange>()
+        .insert(ctx.player, components::ApplyEquipmentChange { item, equip: true })
+        .expect("Unable to insert ApplyEquipmentChange");
+    RunState::PlayerTurn
+}
+
+struct Remove;
+
+impl Command for Remove {
+    fn name(&self) -> &'static str {
+        "remove"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["unequip"]
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["remove <item>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Take off or put away something you're wearing or wielding"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["remove hat", "unequip cutlass"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let x = args.get("item").unwrap();
+        let mut output = ctx.world.write_resource::<Output>();
+        let equipped = ctx.world.read_storage::<components::Equipped>();
+        let ds = ctx.world.read_storage::<components::Description>();
+        let candidates = equipped.get(ctx.player)
+            .map(|equipped| equipped.slots.values())
+            .into_iter()
+            .flatten()
+            .filter_map(|item| ds.get(*item).map(|d| (*item, d)));
+        let not_found = format!("You aren't wearing or wielding anything like \"{}\"", x);
+        match find_target(ctx.player, x, candidates, &mut output, not_found) {
+            Some(item) => {
+                ctx.world.write_storage::<components::ApplyEquipmentChange>()
+                    .insert(ctx.player, components::ApplyEquipmentChange { item, equip: false })
+                    .expect("Unable to insert ApplyEquipmentChange");
+                RunState::PlayerTurn
+            }
+            None => RunState::AwaitingInput,
+        }
+    }
+}
+
+struct ListEquipment;
+
+impl Command for ListEquipment {
+    fn name(&self) -> &'static str {
+        "equipment"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["eq"]
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["equipment"]
+    }
+
+    fn help(&self) -> &'static str {
+        "List everything you're wearing and wielding"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["equipment", "eq"]
+    }
+
+    fn run(&self, ctx: &Context, _args: &Args) -> RunState {
+        let equipped = ctx.world.read_storage::<components::Equipped>();
+        let equippables = ctx.world.read_storage::<components::Equippable>();
+        let ds = ctx.world.read_storage::<components::Description>();
+        let slots = equipped.get(ctx.player).map(|equipped| &equipped.slots);
+        let text = match slots.filter(|slots| !slots.is_empty()) {
+            Some(slots) => {
+                let mut lines = vec!["Your equipment -----".to_string()];
+                for (slot, item) in slots {
+                    let glance = ds.get(*item).map_or("something", |d| d.glance.as_str());
+                    let bonus = equippables.get(*item)
+                        .map(|e| bonus(e.attack, e.defense))
+                        .unwrap_or_default();
+                    lines.push(format!("- {}: {}{}", slot.name(), glance, bonus));
+                }
+                lines.join("\n")
+            }
+            None => "You aren't wearing or wielding anything.".to_string(),
+        };
+        ctx.world.write_resource::<Output>().send(ctx.player, text);
+        RunState::AwaitingInput
+    }
+}
+
+/// Describe what a piece of equipment adds to its wearer's stats, like " (+2 attack)".
+fn bonus(attack: i32, defense: i32) -> String {
+    let mut parts = Vec::new();
+    if attack != 0 {
+        parts.push(format!("{:+} attack", attack));
+    }
+    if defense != 0 {
+        parts.push(format!("{:+} defense", defense));
+    }
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", parts.join(", "))
+    }
+}
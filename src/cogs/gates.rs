@@ -0,0 +1,224 @@
+// Opening, closing, locking and unlocking the gates between rooms.
+// ----------------------------
+use specs::prelude::*;
+
+use crate::cogs::Cog;
+use crate::game::{Message, Output, RunState};
+use crate::game::commands::{find_target, Args, Command, Context};
+use crate::game::components;
+use crate::game::components::helpers::{get_entity_room_id, get_entities_in_storage_as_bitset};
+use crate::game::map::{ExitDirection, Gate, Map, RoomId};
+
+
+pub struct Gates;
+
+impl Cog for Gates {
+    fn name(&self) -> &'static str {
+        "gates"
+    }
+
+    fn commands(&self) -> Vec<Box<dyn Command>> {
+        vec![Box::new(Open), Box::new(Close), Box::new(Unlock), Box::new(Lock)]
+    }
+}
+
+struct Open;
+
+impl Command for Open {
+    fn name(&self) -> &'static str {
+        "open"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["open <direction>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Open the way in a direction"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["open next", "open hatch"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let mut output = ctx.world.write_resource::<Output>();
+        let mut map = ctx.world.write_resource::<Map>();
+        let (room, dir) = match find_exit(ctx, &map, args.get("direction").unwrap(), &mut output) {
+            Some(exit) => exit,
+            None => return RunState::AwaitingInput,
+        };
+        let gate = map.room(&room).exit(dir.clone()).unwrap();
+        if gate.is_locked() {
+            output.send(ctx.player, Message::error("It's locked."));
+            return RunState::AwaitingInput
+        }
+        if !gate.is_closed() {
+            output.send(ctx.player, Message::error("It's already open."));
+            return RunState::AwaitingInput
+        }
+        map.update_gate(&room, &dir, |gate| gate.set_closed(false));
+        output.send(ctx.player, format!("You open the {} exit.", dir.name()));
+        RunState::PlayerTurn
+    }
+}
+
+struct Close;
+
+impl Command for Close {
+    fn name(&self) -> &'static str {
+        "close"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["close <direction>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Close the way in a direction"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["close previous"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let mut output = ctx.world.write_resource::<Output>();
+        let mut map = ctx.world.write_resource::<Map>();
+        let (room, dir) = match find_exit(ctx, &map, args.get("direction").unwrap(), &mut output) {
+            Some(exit) => exit,
+            None => return RunState::AwaitingInput,
+        };
+        if map.room(&room).exit(dir.clone()).unwrap().is_closed() {
+            output.send(ctx.player, Message::error("It's already closed."));
+            return RunState::AwaitingInput
+        }
+        map.update_gate(&room, &dir, |gate| gate.set_closed(true));
+        output.send(ctx.player, format!("You close the {} exit.", dir.name()));
+        RunState::PlayerTurn
+    }
+}
+
+struct Unlock;
+
+impl Command for Unlock {
+    fn name(&self) -> &'static str {
+        "unlock"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["unlock <direction> with <key>", "unlock <direction>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Unlock the way in a direction with a key you carry"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["unlock next with blue keycard", "unlock next"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        set_locked(ctx, args, false)
+    }
+}
+
+struct Lock;
+
+impl Command for Lock {
+    fn name(&self) -> &'static str {
+        "lock"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["lock <direction> with <key>", "lock <direction>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Close and lock the way in a direction with a key you carry"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["lock next with blue keycard", "lock next"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        set_locked(ctx, args, true)
+    }
+}
+
+/// Lock or unlock a gate, if the player has a key to it.
+fn set_locked(ctx: &Context, args: &Args, locked: bool) -> RunState {
+    let mut output = ctx.world.write_resource::<Output>();
+    let mut map = ctx.world.write_resource::<Map>();
+    let (room, dir) = match find_exit(ctx, &map, args.get("direction").unwrap(), &mut output) {
+        Some(exit) => exit,
+        None => return RunState::AwaitingInput,
+    };
+    let gate = map.room(&room).exit(dir.clone()).unwrap();
+    if !gate.has_lock() {
+        output.send(ctx.player, Message::error("There's no lock on it."));
+        return RunState::AwaitingInput
+    }
+    if gate.is_locked() == locked {
+        let error = if locked { "It's already locked." } else { "It's already unlocked." };
+        output.send(ctx.player, Message::error(error));
+        return RunState::AwaitingInput
+    }
+    let key = match find_key(ctx, gate, args.get("key"), &mut output) {
+        Some(key) => key,
+        None => return RunState::AwaitingInput,
+    };
+
+    map.update_gate(&room, &dir, |gate| gate.set_locked(locked));
+    let ds = ctx.world.read_storage::<components::Description>();
+    output.send(ctx.player, format!(
+        "You {} the {} exit with the {}.",
+        if locked { "lock" } else { "unlock" },
+        dir.name(),
+        ds.get(key).unwrap().glance,
+    ));
+    RunState::PlayerTurn
+}
+
+/// Work out which of the exits out of the player's room they mean, telling them if there's none.
+fn find_exit(ctx: &Context, map: &Map, direction: &str, output: &mut Output) -> Option<(RoomId, ExitDirection)> {
+    let room = get_entity_room_id(ctx.player, ctx.world)
+        .expect("Expected player to be in a room");
+    match map.room(&room).find_exit(direction) {
+        Some(dir) => Some((room, dir)),
+        None => {
+            output.send(ctx.player, Message::error(format!("There's no {} exit here", direction.trim())));
+            None
+        }
+    }
+}
+
+/// Find the key the player uses on a gate: the one they name, or otherwise any they carry which fits.
+fn find_key(ctx: &Context, gate: &Gate, key: Option<&str>, output: &mut Output) -> Option<Entity> {
+    let inventory = get_entities_in_storage_as_bitset(ctx.player, ctx.world)
+        .expect("Expected player to have an inventory!");
+    let entities = ctx.world.entities();
+    let ds = ctx.world.read_storage::<components::Description>();
+    match key {
+        Some(key) => {
+            let candidates = (&entities, &ds, &inventory).join().map(|(e, d, _)| (e, d));
+            let not_found = format!("Nothing in your inventory like \"{}\"", key);
+            let key = find_target(ctx.player, key, candidates, output, not_found)?;
+            if !gate.fits(key) {
+                output.send(ctx.player, Message::error(format!("The {} doesn't fit.", ds.get(key).unwrap().glance)));
+                return None
+            }
+            Some(key)
+        }
+        None => {
+            let key = (&entities, &inventory).join()
+                .map(|(e, _)| e)
+                .find(|e| gate.fits(*e));
+            if key.is_none() {
+                output.send(ctx.player, Message::error("You don't have a key which fits."));
+            }
+            key
+        }
+    }
+}
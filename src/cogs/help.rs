@@ -0,0 +1,97 @@
+// Explaining the other commands.
+//
+// Everything shown here comes from the registered commands themselves, so help can't fall out of
+// date as commands are added or changed.
+// ----------------------------
+use specs::WorldExt;
+
+use crate::cogs::Cog;
+use crate::game::{Message, Output, RunState};
+use crate::game::commands::{Args, Command, Commands, Context};
+
+// Width of the column of syntax patterns in the command list.
+const SYNTAX_WIDTH: usize = 30;
+
+
+pub struct Help;
+
+impl Cog for Help {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn commands(&self) -> Vec<Box<dyn Command>> {
+        vec![Box::new(HelpCommand)]
+    }
+}
+
+struct HelpCommand;
+
+impl Command for HelpCommand {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["help <command>", "help"]
+    }
+
+    fn help(&self) -> &'static str {
+        "List the available commands, or explain one of them"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["help", "help get"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let message = match args.get("command") {
+            Some(name) => match ctx.commands.find(name) {
+                Some(command) => help_page(command).into(),
+                None => Message::error(format!(
+                    "There's no command called \"{}\". Type \"help\" for a list of commands.",
+                    name,
+                )),
+            },
+            None => command_list(ctx.commands).into(),
+        };
+        ctx.world.write_resource::<Output>().send(ctx.player, message);
+        RunState::AwaitingInput
+    }
+}
+
+/// List every command with each way of typing it, grouped by cog.
+fn command_list(commands: &Commands) -> String {
+    let mut lines = vec!["Available commands:".to_string()];
+    let mut current_cog = "";
+    for (cog, command) in commands.iter() {
+        if cog != current_cog {
+            lines.push(format!("-- {} --", cog));
+            current_cog = cog;
+        }
+        for (i, syntax) in command.syntax().iter().enumerate() {
+            if i == 0 {
+                lines.push(format!("{: <width$}{}", syntax, command.help(), width = SYNTAX_WIDTH));
+            } else {
+                lines.push(syntax.to_string());
+            }
+        }
+    }
+    lines.push("Type \"help <command>\" for more about a command.".to_string());
+    lines.join("\n")
+}
+
+/// Explain everything about a single command.
+fn help_page(command: &dyn Command) -> String {
+    let mut lines = vec![format!("{} - {}", command.name(), command.help())];
+    lines.push("Usage:".to_string());
+    lines.extend(command.syntax().iter().map(|syntax| format!("  {}", syntax)));
+    if !command.aliases().is_empty() {
+        lines.push(format!("Also typed as: {}", command.aliases().join(", ")));
+    }
+    if !command.examples().is_empty() {
+        lines.push("Examples:".to_string());
+        lines.extend(command.examples().iter().map(|example| format!("  {}", example)));
+    }
+    lines.join("\n")
+}
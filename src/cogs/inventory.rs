@@ -0,0 +1,431 @@
+// Carrying things around, and moving them in and out of containers.
+// ----------------------------
+use specs::prelude::*;
+
+use crate::cogs::Cog;
+use crate::game::{Message, Output, RunState};
+use crate::game::commands::{check_access, find_target, Args, Command, Context};
+use crate::game::targeting::{self, Target};
+use crate::game::components;
+use crate::game::components::{StorageAccess, StorageAction, UseTarget};
+use crate::game::map::Map;
+use crate::game::components::helpers::{
+    get_entity_room_id,
+    get_room_entities_as_bitset,
+    get_entities_in_storage_as_bitset,
+};
+
+
+pub struct Inventory;
+
+impl Cog for Inventory {
+    fn name(&self) -> &'static str {
+        "inventory"
+    }
+
+    fn commands(&self) -> Vec<Box<dyn Command>> {
+        vec![Box::new(Get), Box::new(Put), Box::new(Drop), Box::new(Use), Box::new(ListInventory)]
+    }
+}
+
+struct Get;
+
+impl Command for Get {
+    fn name(&self) -> &'static str {
+        "get"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["get <item> from <container>", "get <item>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Pick something up, or take it out of a container"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["get oboe", "get keycard from cabinet", "get 2.key"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let item = args.get("item").unwrap();
+        match args.get("container") {
+            Some(container) => get_from(ctx, item, container),
+            None => get(ctx, item),
+        }
+    }
+}
+
+fn get(ctx: &Context, x: &str) -> RunState {
+    let mut output = ctx.world.write_resource::<Output>();
+    let items = ctx.world.read_storage::<components::Item>();
+    let room_id = get_entity_room_id(ctx.player, ctx.world)
+        .expect("Expected player to be in a room");
+    let room_entities = get_room_entities_as_bitset(room_id, ctx.world);
+    let descriptions = ctx.world.read_storage::<components::Description>();
+    let entities = ctx.world.entities();
+    let candidates = (&entities, &items, &descriptions, &room_entities).join()
+        .map(|(e, _, d, _)| (e, d));
+    let target = find_target(ctx.player, x, candidates, &mut output, format!("Not able to pickup \"{}\"", x));
+    if let Some(e) = target {
+        let mut changes = ctx.world.write_storage::<components::ApplyInventoryChange>();
+        if changes.contains(e) {
+            output.send(ctx.player, Message::error("Someone else got to it first!"));
+            return RunState::AwaitingInput
+        }
+        changes
+            .insert(e, components::ApplyInventoryChange {
+                from_container: None,
+                to_container: Some(ctx.player),
+                by: ctx.player,
+                stolen: false,
+            })
+            .expect("Unable to insert");
+        RunState::PlayerTurn
+    } else {
+        drop(output);
+        hint_container(ctx, x);
+        RunState::AwaitingInput
+    }
+}
+
+/// When something isn't lying around, but is in one of the containers in the room, point the
+/// player towards getting it from there instead.
+fn hint_container(ctx: &Context, x: &str) {
+    let room_id = get_entity_room_id(ctx.player, ctx.world)
+        .expect("Expected player to be in a room");
+    let room_entities = get_room_entities_as_bitset(room_id, ctx.world);
+    let entities = ctx.world.entities();
+    let ds = ctx.world.read_storage::<components::Description>();
+    let storages = ctx.world.read_storage::<components::Storage>();
+    let accesses = ctx.world.read_storage::<StorageAccess>();
+    for (container, container_description, _, _) in (&entities, &ds, &storages, &room_entities).join() {
+        // Don't give away what's in containers the player can't look in or take from
+        let allowed = accesses.get(container).is_none_or(|access| {
+            [StorageAction::Look, StorageAction::Take].iter()
+                .all(|action| access.check(ctx.player, *action, &container_description.glance).is_ok())
+        });
+        if !allowed {
+            continue
+        }
+        let contents = get_entities_in_storage_as_bitset(container, ctx.world).unwrap();
+        let candidates = (&entities, &ds, &contents).join().map(|(e, d, _)| (e, d));
+        if let Target::Found(item) = targeting::resolve(x, candidates) {
+            let glance = &ds.get(item).unwrap().glance;
+            let hint = format!(
+                "The {} is in the {}. Try \"get {} from {}\"",
+                glance, container_description.glance, x, container_description.glance,
+            );
+            ctx.world.write_resource::<Output>().send(ctx.player, hint);
+            return
+        }
+    }
+}
+
+fn get_from(ctx: &Context, item_name: &str, container_name: &str) -> RunState {
+    let mut output = ctx.world.write_resource::<Output>();
+    let entities = ctx.world.entities();
+    let room_id = get_entity_room_id(ctx.player, ctx.world)
+        .expect("Expect player to be in a room");
+    let ds = ctx.world.read_storage::<components::Description>();
+    let storages = ctx.world.read_storage::<components::Storage>();
+    let room_entities = get_room_entities_as_bitset(room_id, ctx.world);
+    let candidates = (&entities, &ds, &room_entities).join().map(|(e, d, _)| (e, d));
+    let not_found = format!("Could not find \"{}\" in the current room", container_name);
+    let container = match find_target(ctx.player, container_name, candidates, &mut output, not_found) {
+        Some(container) => container,
+        None => return RunState::AwaitingInput,
+    };
+    let container_glance = ds.get(container).unwrap().glance.clone();
+    if !storages.contains(container) {
+        output.send(ctx.player, Message::error(format!("{} cannot store items", container_glance)));
+        return RunState::AwaitingInput
+    }
+    if !check_access(ctx, container, StorageAction::Take, &mut output) {
+        return RunState::AwaitingInput
+    }
+
+    let container_items = get_entities_in_storage_as_bitset(container, ctx.world).unwrap();
+    let candidates = (&entities, &ds, &container_items).join().map(|(e, d, _)| (e, d));
+    let not_found = format!("Nothing in the {} like \"{}\"", container_glance, item_name);
+    let item = match find_target(ctx.player, item_name, candidates, &mut output, not_found) {
+        Some(item) => item,
+        None => return RunState::AwaitingInput,
+    };
+    let mut changes = ctx.world.write_storage::<components::ApplyInventoryChange>();
+    if changes.contains(item) {
+        output.send(ctx.player, Message::error("Someone else got to it first!"));
+        return RunState::AwaitingInput
+    }
+    changes.insert(item, components::ApplyInventoryChange {
+        from_container: Some(container),
+        to_container: Some(ctx.player),
+        by: ctx.player,
+        stolen: false,
+    }).expect("Could not insert ApplyInventoryChange");
+    RunState::PlayerTurn
+}
+
+struct Put;
+
+impl Command for Put {
+    fn name(&self) -> &'static str {
+        "put"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["put <item> in <container>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Put something you carry in a container"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["put oboe in cabinet"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let (item_name, container_name) = (args.get("item").unwrap(), args.get("container").unwrap());
+        let mut output = ctx.world.write_resource::<Output>();
+        let entities = ctx.world.entities();
+        let player_items = get_entities_in_storage_as_bitset(ctx.player, ctx.world)
+            .expect("Expected player to have an inventory!");
+        let ds = ctx.world.read_storage::<components::Description>();
+        let storages = ctx.world.read_storage::<components::Storage>();
+
+        let candidates = (&entities, &ds, &player_items).join().map(|(e, d, _)| (e, d));
+        let not_found = format!("Nothing in your inventory like \"{}\"", item_name);
+        let item = match find_target(ctx.player, item_name, candidates, &mut output, not_found) {
+            Some(item) => item,
+            None => return RunState::AwaitingInput,
+        };
+
+        let room_id = get_entity_room_id(ctx.player, ctx.world)
+            .expect("Expected player to be in a room");
+        let room_items = get_room_entities_as_bitset(room_id, ctx.world);
+        let candidates = (&entities, &ds, &room_items).join().map(|(e, d, _)| (e, d));
+        let not_found = format!("Could not find \"{}\" in the current room", container_name);
+        let container = match find_target(ctx.player, container_name, candidates, &mut output, not_found) {
+            Some(container) => container,
+            None => return RunState::AwaitingInput,
+        };
+        if !storages.contains(container) {
+            output.send(ctx.player, Message::error(format!("{} cannot store items", ds.get(container).unwrap().glance)));
+            return RunState::AwaitingInput
+        }
+        if !check_access(ctx, container, StorageAction::Put, &mut output) {
+            return RunState::AwaitingInput
+        }
+
+        let mut changes = ctx.world.write_storage::<components::ApplyInventoryChange>();
+        if changes.contains(item) {
+            output.send(ctx.player, Message::error(format!("The {} is already being moved.", ds.get(item).unwrap().glance)));
+            return RunState::AwaitingInput
+        }
+        changes.insert(item, components::ApplyInventoryChange {
+            from_container: Some(ctx.player),
+            to_container: Some(container),
+            by: ctx.player,
+            stolen: false,
+        }).expect("Could not insert ApplyInventoryChange");
+        RunState::PlayerTurn
+    }
+}
+
+struct Drop;
+
+impl Command for Drop {
+    fn name(&self) -> &'static str {
+        "drop"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["drop <item>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Drop something you carry on the ground"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["drop oboe"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let x = args.get("item").unwrap();
+        let mut output = ctx.world.write_resource::<Output>();
+        let inv_items = get_entities_in_storage_as_bitset(ctx.player, ctx.world)
+            .expect("Expected player to have an inventory!");
+        let ds = ctx.world.read_storage::<components::Description>();
+        let entities = ctx.world.entities();
+        let candidates = (&entities, &ds, &inv_items).join().map(|(e, d, _)| (e, d));
+        let not_found = format!("Nothing in your inventory like \"{}\"", x);
+        if let Some(target) = find_target(ctx.player, x, candidates, &mut output, not_found) {
+            let mut changes = ctx.world.write_storage::<components::ApplyInventoryChange>();
+            if changes.contains(target) {
+                output.send(ctx.player, Message::error(format!("The {} is already being moved.", ds.get(target).unwrap().glance)));
+                return RunState::AwaitingInput
+            }
+            changes.insert(target, components::ApplyInventoryChange {
+                from_container: Some(ctx.player),
+                to_container: None,
+                by: ctx.player,
+                stolen: false,
+            }).expect("Could not insert ApplyInventorChange");
+            RunState::PlayerTurn
+        } else {
+            RunState::AwaitingInput
+        }
+    }
+}
+
+struct Use;
+
+impl Command for Use {
+    fn name(&self) -> &'static str {
+        "use"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["use <item> on <target>", "use <item>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Use something you carry or can reach, on its own or on something else"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["use tonic", "use keycard on card-reader door", "use keycard on next"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let x = args.get("item").unwrap();
+        let mut output = ctx.world.write_resource::<Output>();
+        let entities = ctx.world.entities();
+        let ds = ctx.world.read_storage::<components::Description>();
+        let within_reach = within_reach(ctx);
+        let candidates = (&entities, &ds, &within_reach).join().map(|(e, d, _)| (e, d));
+        let not_found = format!("Nothing like \"{}\" to use here", x);
+        let item = match find_target(ctx.player, x, candidates, &mut output, not_found) {
+            Some(item) => item,
+            None => return RunState::AwaitingInput,
+        };
+        if !ctx.world.read_storage::<components::Usable>().contains(item) {
+            let error = format!("You can't think of a way to use the {}.", ds.get(item).unwrap().glance);
+            output.send(ctx.player, Message::error(error));
+            return RunState::AwaitingInput
+        }
+        let target = match args.get("target") {
+            Some(target) => match find_use_target(ctx, target, &mut output) {
+                Some(target) => Some(target),
+                None => return RunState::AwaitingInput,
+            },
+            None => None,
+        };
+
+        ctx.world.write_storage::<components::WantsToUse>()
+            .insert(ctx.player, components::WantsToUse { item, target })
+            .expect("Unable to insert WantsToUse");
+        RunState::PlayerTurn
+    }
+}
+
+/// Everything the player carries, along with everything else in their room.
+fn within_reach(ctx: &Context) -> BitSet {
+    let room_id = get_entity_room_id(ctx.player, ctx.world)
+        .expect("Expected player to be in a room");
+    let mut reach = get_room_entities_as_bitset(room_id, ctx.world);
+    reach |= &get_entities_in_storage_as_bitset(ctx.player, ctx.world)
+        .expect("Expected player to have an inventory!");
+    reach.remove(ctx.player.id());
+    reach
+}
+
+/// Work out what the player is using something on: themselves, something within reach, or one
+/// of the exits, named by its direction or by what it looks like ("card-reader door").
+fn find_use_target(ctx: &Context, x: &str, output: &mut Output) -> Option<UseTarget> {
+    if ["me", "myself", "self"].contains(&x.trim().to_lowercase().as_str()) {
+        return Some(UseTarget::Entity(ctx.player))
+    }
+    let room_id = get_entity_room_id(ctx.player, ctx.world)
+        .expect("Expected player to be in a room");
+    let map = ctx.world.read_resource::<Map>();
+    let room = map.room(&room_id);
+    if let Some(dir) = room.find_exit(x) {
+        return Some(UseTarget::Exit(dir))
+    }
+
+    let entities = ctx.world.entities();
+    let ds = ctx.world.read_storage::<components::Description>();
+    let within_reach = within_reach(ctx);
+    let candidates = || (&entities, &ds, &within_reach).join().map(|(e, d, _)| (e, d));
+    match targeting::resolve(x, candidates()) {
+        Target::Found(target) => Some(UseTarget::Entity(target)),
+        Target::Ambiguous(_) => {
+            // Let find_target explain which ones the player could mean
+            find_target(ctx.player, x, candidates(), output, String::new()).map(UseTarget::Entity)
+        }
+        Target::NotFound => {
+            let words = description_words(x);
+            let exit = room.exits.iter()
+                .find(|(_, gate)| {
+                    let description = description_words(&gate.description);
+                    !words.is_empty() && words.iter().all(|word| description.contains(word))
+                })
+                .map(|(dir, _)| dir.clone());
+            if exit.is_none() {
+                output.send(ctx.player, Message::error(format!("There's nothing like \"{}\" here", x)));
+            }
+            exit.map(UseTarget::Exit)
+        }
+    }
+}
+
+fn description_words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric() && c != '-')
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+struct ListInventory;
+
+impl Command for ListInventory {
+    fn name(&self) -> &'static str {
+        "inventory"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["inv"]
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["inventory"]
+    }
+
+    fn help(&self) -> &'static str {
+        "List everything you carry"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["inventory", "inv"]
+    }
+
+    fn run(&self, ctx: &Context, _args: &Args) -> RunState {
+        let mut output = ctx.world.write_resource::<Output>();
+        let storages = ctx.world.read_storage::<components::Storage>();
+        let ds = ctx.world.read_storage::<components::Description>();
+        let player_storage = storages.get(ctx.player).expect("Expected player to have a storage component");
+        let mut items : Vec<String> = Vec::new();
+        for entity in &player_storage.items {
+            let desc = ds.get(*entity)
+                .unwrap_or_else(|| panic!("Entity {} in player's inventory does not have the Description component!", entity.id()));
+            items.push(desc.glance.clone());
+        }
+        output.send(ctx.player, Message::Inventory { container: None, items });
+
+        RunState::AwaitingInput
+    }
+}
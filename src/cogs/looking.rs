@@ -0,0 +1,136 @@
+// Looking around rooms, at things, and inside of them.
+// ----------------------------
+use specs::prelude::*;
+
+use crate::cogs::Cog;
+use crate::game::{Message, Output, RunState};
+use crate::game::commands::{check_access, find_target, Args, Command, Context};
+use crate::game::components;
+use crate::game::components::StorageAction;
+use crate::game::components::helpers::{
+    get_entity_room_id,
+    get_room_entities_as_bitset,
+    get_room_players_as_descriptions,
+    get_entities_in_storage_as_bitset,
+};
+use crate::game::map::Map;
+
+
+pub struct Looking;
+
+impl Cog for Looking {
+    fn name(&self) -> &'static str {
+        "looking"
+    }
+
+    fn commands(&self) -> Vec<Box<dyn Command>> {
+        vec![Box::new(Look)]
+    }
+}
+
+struct Look;
+
+impl Command for Look {
+    fn name(&self) -> &'static str {
+        "look"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["look in <container>", "look [at] <target>", "look"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Describe the current room, an object by name, or what's inside a container"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["look", "look at robot pirate", "look in cabinet"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        if let Some(container) = args.get("container") {
+            look_in(ctx, container)
+        } else if let Some(target) = args.get("target") {
+            look_at(ctx, target)
+        } else {
+            look(ctx)
+        }
+    }
+}
+
+fn look(ctx: &Context) -> RunState {
+    let mut output = ctx.world.write_resource::<Output>();
+    let mut inrooms = ctx.world.write_storage::<components::InRoom>();
+    let room_id = inrooms.get(ctx.player).unwrap().room;
+    let map = ctx.world.read_resource::<Map>();
+    let descriptions = ctx.world.read_storage::<components::Description>();
+    let names = ctx.world.read_storage::<components::DisplayName>();
+    output.send(ctx.player, map.room(&room_id).description(
+        ctx.player,
+        &ctx.world.entities(),
+        &mut inrooms,
+        &descriptions,
+        &names,
+    ));
+
+    RunState::AwaitingInput
+}
+
+fn look_at(ctx: &Context, x: &str) -> RunState {
+    let mut output = ctx.world.write_resource::<Output>();
+    let room_id = get_entity_room_id(ctx.player, ctx.world)
+        .expect("Expected player to be in a room!");
+    let room_entities = get_room_entities_as_bitset(room_id, ctx.world);
+    let player_inventory_entities = get_entities_in_storage_as_bitset(ctx.player, ctx.world)
+        .expect("Expected player to have an inventory!");
+    let lookables = room_entities | player_inventory_entities;
+    let ds = ctx.world.read_storage::<components::Description>();
+
+    let entities = ctx.world.entities();
+    let players = get_room_players_as_descriptions(room_id, ctx.player, ctx.world);
+    let candidates = (&entities, &ds, &lookables).join()
+        .map(|(e, d, _)| (e, d))
+        .chain(players.iter().map(|(e, d)| (*e, d)));
+    if let Some(target) = find_target(ctx.player, x, candidates, &mut output, format!("Could not find \"{}\"", x)) {
+        let description = ds.get(target)
+            .or_else(|| players.iter().find(|(e, _)| *e == target).map(|(_, d)| d))
+            .unwrap();
+        output.send(ctx.player, description.description.clone());
+    }
+    RunState::AwaitingInput
+}
+
+fn look_in(ctx: &Context, x: &str) -> RunState {
+    let mut output = ctx.world.write_resource::<Output>();
+    let storages = ctx.world.read_storage::<components::Storage>();
+    let ds = ctx.world.read_storage::<components::Description>();
+    let room_id = get_entity_room_id(ctx.player, ctx.world)
+        .expect("Expected player to be in a room");
+    let room_entities = get_room_entities_as_bitset(room_id, ctx.world);
+    let entities = ctx.world.entities();
+    let candidates = (&entities, &ds, &room_entities).join().map(|(e, d, _)| (e, d));
+    let target = find_target(ctx.player, x, candidates, &mut output, format!("Nothing in the room like \"{}\"", x));
+
+    if let Some(e) = target {
+        if !storages.contains(e) {
+            output.send(ctx.player, Message::error(format!("You can't store items in {}!", ds.get(e).unwrap().glance)));
+            return RunState::PlayerTurn
+        }
+        if !check_access(ctx, e, StorageAction::Look, &mut output) {
+            return RunState::AwaitingInput
+        }
+        let container = storages.get(e).unwrap();
+        let container_desc = ds.get(e).unwrap();
+        let mut items: Vec<String> = Vec::new();
+        for item in &container.items {
+            let item_desc = ds.get(*item)
+                .unwrap_or_else(|| panic!("Expected item {} in container {} to have a Description component!", item.id(), e.id()));
+            items.push(item_desc.glance.clone());
+        }
+        output.send(ctx.player, Message::Inventory {
+            container: Some(container_desc.glance.clone()),
+            items,
+        });
+    }
+    RunState::PlayerTurn
+}
@@ -0,0 +1,40 @@
+// Cogs are bundles of related commands, which can be plugged in to a game.
+//
+// Each cog lives in its own module, and hands its commands over to be registered when a game is
+// created. New verbs go in whichever cog they belong with, or in a new cog added to
+// `default_cogs`.
+// ----------------------------
+mod combat;
+mod dialogue;
+mod equipment;
+mod gates;
+mod help;
+mod inventory;
+mod looking;
+mod movement;
+mod thievery;
+
+use crate::game::commands::Command;
+
+
+/// A bundle of related commands.
+pub trait Cog {
+    fn name(&self) -> &'static str;
+
+    fn commands(&self) -> Vec<Box<dyn Command>>;
+}
+
+/// The cogs every game starts with.
+pub fn default_cogs() -> Vec<Box<dyn Cog>> {
+    vec![
+        Box::new(movement::Movement),
+        Box::new(gates::Gates),
+        Box::new(looking::Looking),
+        Box::new(inventory::Inventory),
+        Box::new(equipment::Equipment),
+        Box::new(combat::Combat),
+        Box::new(thievery::Thievery),
+        Box::new(dialogue::Dialogue),
+        Box::new(help::Help),
+    ]
+}
@@ -0,0 +1,79 @@
+// Moving between rooms.
+// ----------------------------
+use specs::prelude::*;
+
+use crate::cogs::Cog;
+use crate::game::{Message, Output, RunState};
+use crate::game::commands::{Args, Command, Context};
+use crate::game::components;
+use crate::game::map::Map;
+
+
+pub struct Movement;
+
+impl Cog for Movement {
+    fn name(&self) -> &'static str {
+        "movement"
+    }
+
+    fn commands(&self) -> Vec<Box<dyn Command>> {
+        vec![Box::new(Go)]
+    }
+}
+
+struct Go;
+
+impl Command for Go {
+    fn name(&self) -> &'static str {
+        "go"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["go <direction>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Go through an exit, which can also be done by just naming it"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["go north", "north", "n", "next", "up", "climb ladder"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        go(ctx, args.get("direction").unwrap())
+    }
+}
+
+/// Move the player through the exit they mean, if there is one and it isn't shut.
+fn go(ctx: &Context, phrase: &str) -> RunState {
+    let mut output = ctx.world.write_resource::<Output>();
+    let mut apply_moves = ctx.world.write_storage::<components::ApplyMove>();
+    let inrooms = ctx.world.read_storage::<components::InRoom>();
+    let inroom = inrooms.get(ctx.player).unwrap();
+    let mut map = ctx.world.write_resource::<Map>();
+    let room = map.room(&inroom.room);
+    match room.find_exit(phrase) {
+        Some(direction) => {
+            let g = room.exit(direction.clone()).unwrap();
+            if g.is_locked() {
+                output.send(ctx.player, Message::error("That path is locked!"));
+            } else if g.is_closed() {
+                output.send(ctx.player, Message::error(format!("The {} exit is closed.", direction.name())));
+            } else {
+                apply_moves
+                    .insert(ctx.player, components::ApplyMove{room: g.to()})
+                    .expect("Unable to insert");
+                if g.relocks() {
+                    map.update_gate(&inroom.room, &direction, |gate| {
+                        gate.set_closed(true);
+                        gate.set_locked(gate.has_lock());
+                    });
+                    output.send(ctx.player, "The way shuts behind you.");
+                }
+            }
+        }
+        None => output.send(ctx.player, Message::error(format!("You can't go {} from here", phrase.trim()))),
+    }
+    RunState::PlayerTurn
+}
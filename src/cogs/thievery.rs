@@ -0,0 +1,127 @@
+// Stealing from NPCs.
+// ----------------------------
+use specs::prelude::*;
+
+use crate::cogs::Cog;
+use crate::game::{Message, Output, RunState};
+use crate::game::commands::{find_target, Args, Command, Context};
+use crate::game::components;
+use crate::game::components::helpers::{get_entity_room_id, get_room_entities_as_bitset};
+
+
+pub struct Thievery;
+
+impl Cog for Thievery {
+    fn name(&self) -> &'static str {
+        "thievery"
+    }
+
+    fn commands(&self) -> Vec<Box<dyn Command>> {
+        vec![Box::new(Steal), Box::new(Pickpocket)]
+    }
+}
+
+struct Steal;
+
+impl Command for Steal {
+    fn name(&self) -> &'static str {
+        "steal"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["steal <item> from <target>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Try to steal something from someone without them noticing"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["steal doubloon from robot pirate", "steal bolt from 2.robot"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let item_name = args.get("item").unwrap();
+        // Output is written to again by the attempt, so it has to be let go of first
+        let (npc, item) = {
+            let mut output = ctx.world.write_resource::<Output>();
+            let npc = match find_mark(ctx, args.get("target").unwrap(), &mut output) {
+                Some(npc) => npc,
+                None => return RunState::AwaitingInput,
+            };
+            let entities = ctx.world.entities();
+            let ds = ctx.world.read_storage::<components::Description>();
+            let storages = ctx.world.read_storage::<components::Storage>();
+            let pockets = storages.get(npc).map_or(&[][..], |storage| storage.items.as_slice());
+            let candidates = pockets.iter()
+                .filter_map(|item| ds.get(*item).map(|d| (*item, d)))
+                .filter(|(item, _)| entities.is_alive(*item));
+            let not_found = format!("The {} doesn't seem to carry anything like \"{}\"", ds.get(npc).unwrap().glance, item_name);
+            match find_target(ctx.player, item_name, candidates, &mut output, not_found) {
+                Some(item) => (npc, item),
+                None => return RunState::AwaitingInput,
+            }
+        };
+        attempt(ctx, npc, Some(item))
+    }
+}
+
+struct Pickpocket;
+
+impl Command for Pickpocket {
+    fn name(&self) -> &'static str {
+        "pickpocket"
+    }
+
+    fn syntax(&self) -> &'static [&'static str] {
+        &["pickpocket <target>"]
+    }
+
+    fn help(&self) -> &'static str {
+        "Try to take whatever you can grab from someone's pockets"
+    }
+
+    fn examples(&self) -> &'static [&'static str] {
+        &["pickpocket robot pirate"]
+    }
+
+    fn run(&self, ctx: &Context, args: &Args) -> RunState {
+        let npc = {
+            let mut output = ctx.world.write_resource::<Output>();
+            match find_mark(ctx, args.get("target").unwrap(), &mut output) {
+                Some(npc) => npc,
+                None => return RunState::AwaitingInput,
+            }
+        };
+        attempt(ctx, npc, None)
+    }
+}
+
+/// Find the NPC in the room the player means to steal from. Only NPCs with pockets can be
+/// stolen from.
+fn find_mark(ctx: &Context, x: &str, output: &mut Output) -> Option<Entity> {
+    let room_id = get_entity_room_id(ctx.player, ctx.world)
+        .expect("Expected player to be in a room");
+    let room_entities = get_room_entities_as_bitset(room_id, ctx.world);
+    let entities = ctx.world.entities();
+    let ds = ctx.world.read_storage::<components::Description>();
+    let npcs = ctx.world.read_storage::<components::Npc>();
+    let storages = ctx.world.read_storage::<components::Storage>();
+    let candidates = (&entities, &ds, &npcs, &storages, &room_entities).join().map(|(e, d, _, _, _)| (e, d));
+    let not_found = format!("There's nobody like \"{}\" here with pockets to pick", x);
+    find_target(ctx.player, x, candidates, output, not_found)
+}
+
+/// Try to steal from an NPC on the player's turn, unless it's still keeping an eye out.
+fn attempt(ctx: &Context, npc: Entity, item: Option<Entity>) -> RunState {
+    if ctx.world.read_storage::<components::Wary>().contains(npc) {
+        let glance = ctx.world.read_storage::<components::Description>().get(npc).unwrap().glance.clone();
+        let wary = format!("The {} is keeping a close eye on its belongings.", glance);
+        ctx.world.write_resource::<Output>().send(ctx.player, Message::error(wary));
+        return RunState::AwaitingInput
+    }
+    ctx.world.write_storage::<components::WantsToSteal>()
+        .insert(ctx.player, components::WantsToSteal { from: npc, item })
+        .expect("Unable to insert WantsToSteal");
+    RunState::PlayerTurn
+}
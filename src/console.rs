@@ -0,0 +1,42 @@
+// Console front-end.
+//
+// A reader thread feeds lines from stdin into the game's input queue, so the terminal is just
+// another producer of player input.
+// ----------------------------
+use std::io::{self, BufRead};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::game::{ConsoleSink, Game};
+
+
+/// Play the game from the terminal until stdin is closed.
+pub fn run() {
+    println!("Creating the game object");
+    let mut g = Game::new();
+    let player = g.login("console").expect("Failed to log in to the console account");
+
+    let (tx, rx) = mpsc::channel::<String>();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            match line {
+                Ok(line) => if tx.send(line).is_err() { return },
+                Err(_) => return,
+            }
+        }
+    });
+
+    loop {
+        g.run_until_idle();
+        g.flush_output(&mut ConsoleSink);
+
+        println!("Please input a command");
+        match rx.recv() {
+            Ok(line) => g.player_input(player, &line),
+            Err(_) => return,
+        }
+        while let Ok(line) = rx.try_recv() {
+            g.player_input(player, &line);
+        }
+    }
+}
@@ -0,0 +1,448 @@
+// Discord front-end.
+//
+// Every configured channel owns its own game, with the channel acting as that game's player.
+// Players can also play solo by messaging the bot directly, which gives each of them a private
+// game. If a boss channel is configured, reaching the boss room in a solo game moves the player
+// into the boss channel's shared game, where everyone who made it fights alongside each other.
+//
+// Games run on dedicated threads (a specs dispatcher can't be moved between tokio tasks), and
+// whatever they output is posted back by a task per channel. When a save directory is configured,
+// every game is saved there after each round of input and picked back up after a restart.
+// ----------------------------
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use serenity::async_trait;
+use serenity::http::Http;
+use serenity::model::channel::Message;
+use serenity::model::gateway::Ready;
+use serenity::model::id::{ChannelId, UserId};
+use serenity::model::mention::Mentionable;
+use serenity::prelude::*;
+use specs::world::Index as EntityId;
+use tokio::sync::mpsc as async_mpsc;
+
+use crate::game::{self, Game, OutputSink, PlayerState, RoomId};
+
+// Discord rejects messages longer than this many characters.
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+/// The shared channel players are promoted into once they reach the boss room on their own.
+#[derive(Clone)]
+struct Boss {
+    channel: ChannelId,
+    room: RoomId,
+    events: mpsc::Sender<BossEvent>,
+    promoted: Arc<Mutex<HashSet<UserId>>>,
+}
+
+enum BossEvent {
+    // A player finished their solo run and is joining the shared game.
+    Join { user: UserId, name: String, state: PlayerState },
+
+    // A message posted in the boss channel.
+    Input { user: UserId, name: String, text: String },
+}
+
+struct Handler {
+    channels: Vec<ChannelId>,
+    games: Mutex<HashMap<ChannelId, mpsc::Sender<String>>>,
+    solo_games: Mutex<HashMap<UserId, mpsc::Sender<String>>>,
+    boss: Option<Boss>,
+    saves: Option<PathBuf>,
+}
+
+impl Handler {
+    async fn solo_message(&self, ctx: Context, msg: Message) {
+        if let Some(boss) = &self.boss {
+            let promoted = boss.promoted.lock().unwrap().contains(&msg.author.id);
+            if promoted {
+                let reply = format!("Your adventure continues in {}!", boss.channel.mention());
+                if let Err(e) = msg.channel_id.say(&ctx.http, reply).await {
+                    log::error!("Failed to send message to channel {}: {:?}", msg.channel_id, e);
+                }
+                return
+            }
+        }
+
+        let mut games = self.solo_games.lock().unwrap();
+        let game = games.entry(msg.author.id).or_insert_with(|| {
+            let outputs = start_posting(msg.channel_id, ctx.http.clone());
+            let (tx, rx) = mpsc::channel::<String>();
+            let (user, name, boss) = (msg.author.id, msg.author.name.clone(), self.boss.clone());
+            let save = save_path(&self.saves, &format!("user-{}", user));
+            thread::spawn(move || run_solo_game(rx, outputs, user, name, boss, save));
+            tx
+        });
+        if game.send(msg.content).is_err() {
+            games.remove(&msg.author.id);
+        }
+    }
+
+    fn channel_message(&self, ctx: Context, msg: Message) {
+        let mut games = self.games.lock().unwrap();
+        let game = games.entry(msg.channel_id).or_insert_with(|| {
+            let outputs = start_posting(msg.channel_id, ctx.http.clone());
+            let (tx, rx) = mpsc::channel::<String>();
+            let channel = msg.channel_id;
+            let save = save_path(&self.saves, &format!("channel-{}", channel));
+            thread::spawn(move || run_channel_game(rx, outputs, channel, save));
+            tx
+        });
+        if game.send(msg.content).is_err() {
+            log::error!("Game for channel {} has stopped", msg.channel_id);
+            games.remove(&msg.channel_id);
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn message(&self, ctx: Context, msg: Message) {
+        if msg.author.bot {
+            return
+        }
+
+        if msg.guild_id.is_none() {
+            self.solo_message(ctx, msg).await;
+        } else if self.boss.as_ref().map_or(false, |boss| boss.channel == msg.channel_id) {
+            let boss = self.boss.as_ref().unwrap();
+            let event = BossEvent::Input { user: msg.author.id, name: msg.author.name, text: msg.content };
+            if boss.events.send(event).is_err() {
+                log::error!("Game for boss channel {} has stopped", msg.channel_id);
+            }
+        } else if self.channels.contains(&msg.channel_id) {
+            self.channel_message(ctx, msg);
+        }
+    }
+
+    async fn ready(&self, _: Context, ready: Ready) {
+        println!("Connected to Discord as {}", ready.user.name);
+    }
+}
+
+/// Run the Discord bot until the connection is closed.
+///
+/// Configuration is read from the environment:
+///
+/// - `DISCORD_TOKEN`: the bot token.
+/// - `DISCORD_CHANNELS`: optional comma separated ids of channels which each host a game.
+/// - `DISCORD_BOSS_CHANNEL`: optional id of the channel hosting the shared boss game.
+/// - `DISCORD_BOSS_ROOM`: id (from the map file) of the room which promotes solo players into the
+///   boss channel, required when a boss channel is set.
+/// - `DISCORD_SAVE_DIR`: optional directory the games are saved to.
+pub fn run() {
+    let token = env::var("DISCORD_TOKEN")
+        .expect("Expected a bot token in the DISCORD_TOKEN environment variable");
+    let channels = env::var("DISCORD_CHANNELS")
+        .unwrap_or_default()
+        .split(',')
+        .filter(|id| !id.trim().is_empty())
+        .map(|id| ChannelId(id.trim().parse().expect("Channel ids in DISCORD_CHANNELS must be numbers")))
+        .collect::<Vec<ChannelId>>();
+    let boss = env::var("DISCORD_BOSS_CHANNEL").ok().map(|id| {
+        let channel = ChannelId(id.trim().parse().expect("DISCORD_BOSS_CHANNEL must be a channel id"));
+        let name = env::var("DISCORD_BOSS_ROOM")
+            .expect("Expected a room id in the DISCORD_BOSS_ROOM environment variable");
+        // Every game is built from the same map, so the room is the same in all of them
+        let room = Game::new()
+            .room_named(name.trim())
+            .unwrap_or_else(|| panic!("DISCORD_BOSS_ROOM names no room on the map: {:?}", name.trim()));
+        (channel, room)
+    });
+    let saves = env::var("DISCORD_SAVE_DIR").ok().map(PathBuf::from);
+    if let Some(dir) = &saves {
+        fs::create_dir_all(dir).unwrap_or_else(|e| panic!("Failed to create save directory {:?}: {:?}", dir, e));
+    }
+
+    let intents = GatewayIntents::GUILD_MESSAGES
+        | GatewayIntents::DIRECT_MESSAGES
+        | GatewayIntents::MESSAGE_CONTENT;
+
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start the async runtime");
+    runtime.block_on(async {
+        let handler = Handler {
+            channels,
+            games: Mutex::new(HashMap::new()),
+            solo_games: Mutex::new(HashMap::new()),
+            boss: boss.map(|(channel, room)| {
+                start_boss_game(channel, room, Arc::new(Http::new(&token)), save_path(&saves, "boss"))
+            }),
+            saves,
+        };
+
+        let mut client = Client::builder(&token, intents)
+            .event_handler(handler)
+            .await
+            .expect("Failed to create the Discord client");
+        if let Err(e) = client.start().await {
+            log::error!("Discord client stopped: {:?}", e);
+        }
+    });
+}
+
+/// Start a task posting everything sent on the returned sender to a channel.
+fn start_posting(channel: ChannelId, http: Arc<Http>) -> async_mpsc::UnboundedSender<String> {
+    let (tx, mut rx) = async_mpsc::unbounded_channel::<String>();
+    tokio::spawn(async move {
+        while let Some(text) = rx.recv().await {
+            for chunk in split_message(&text) {
+                if let Err(e) = channel.say(&http, chunk).await {
+                    log::error!("Failed to send message to channel {}: {:?}", channel, e);
+                }
+            }
+        }
+    });
+    tx
+}
+
+fn start_boss_game(channel: ChannelId, room: RoomId, http: Arc<Http>, save: Option<PathBuf>) -> Boss {
+    let outputs = start_posting(channel, http);
+    let (tx, rx) = mpsc::channel::<BossEvent>();
+    let promoted = Arc::new(Mutex::new(HashSet::new()));
+    let boss_promoted = promoted.clone();
+    thread::spawn(move || run_boss_game(rx, outputs, boss_promoted, save));
+    Boss {
+        channel,
+        room,
+        events: tx,
+        promoted,
+    }
+}
+
+/// Where a game is saved, if games are being saved at all.
+fn save_path(saves: &Option<PathBuf>, name: &str) -> Option<PathBuf> {
+    saves.as_ref().map(|dir| dir.join(format!("{}.json", name)))
+}
+
+/// Pick a game back up from its save file, or start a new one if it has never been saved.
+fn open_game(save: Option<&Path>) -> Game<'static, 'static> {
+    let mut game = Game::new();
+    if let Some(path) = save.filter(|path| path.exists()) {
+        if let Err(e) = game.load(path) {
+            log::error!("Failed to load game from {:?}, starting a new one: {}", path, e);
+            game = Game::new();
+        }
+    }
+    game
+}
+
+fn save_game(game: &mut Game, save: Option<&Path>) {
+    if let Some(path) = save {
+        if let Err(e) = game.save(path) {
+            log::error!("Failed to save game to {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Forwards game output to the task posting messages to a channel.
+///
+/// When several players share the channel, their output is headed with their name.
+struct ChannelSink {
+    outputs: async_mpsc::UnboundedSender<String>,
+    names: HashMap<EntityId, String>,
+    closed: bool,
+}
+
+impl ChannelSink {
+    fn new(outputs: async_mpsc::UnboundedSender<String>) -> Self {
+        ChannelSink { outputs, names: HashMap::new(), closed: false }
+    }
+}
+
+impl OutputSink for ChannelSink {
+    fn send(&mut self, player: EntityId, messages: Vec<game::Message>) {
+        let mut text = messages.iter()
+            .map(|message| message.to_string())
+            .collect::<Vec<String>>()
+            .join("\n");
+        if let Some(name) = self.names.get(&player) {
+            text = format!("**{}**\n{}", name, text);
+        }
+        if self.outputs.send(text).is_err() {
+            self.closed = true;
+        }
+    }
+}
+
+/// Game loop for a channel's own game. Returns once the channel's input sender is dropped.
+fn run_channel_game(
+    inputs: mpsc::Receiver<String>,
+    outputs: async_mpsc::UnboundedSender<String>,
+    channel: ChannelId,
+    save: Option<PathBuf>,
+) {
+    let mut game = open_game(save.as_deref());
+    let player = game.login(&channel_account(channel)).expect("Expected the channel to be logged out");
+    let mut sink = ChannelSink::new(outputs);
+
+    loop {
+        game.run_until_idle();
+        game.flush_output(&mut sink);
+        save_game(&mut game, save.as_deref());
+        if sink.closed {
+            return
+        }
+
+        match inputs.recv() {
+            Ok(input) => game.player_input(player, &input),
+            Err(_) => return,
+        }
+    }
+}
+
+/// Game loop for a player's private game, which ends early if they are promoted to the boss
+/// channel.
+fn run_solo_game(
+    inputs: mpsc::Receiver<String>,
+    outputs: async_mpsc::UnboundedSender<String>,
+    user: UserId,
+    name: String,
+    boss: Option<Boss>,
+    save: Option<PathBuf>,
+) {
+    let mut game = open_game(save.as_deref());
+    let player = game.login(&user_account(user)).expect("Expected the player to be logged out");
+    game.set_player_name(player, &name);
+    let mut sink = ChannelSink::new(outputs);
+
+    loop {
+        game.run_until_idle();
+        game.flush_output(&mut sink);
+        if sink.closed {
+            return
+        }
+
+        if let Some(boss) = &boss {
+            if game.player_room(player) == Some(boss.room) {
+                let state = game.remove_player(player).expect("Expected solo player to exist");
+                boss.promoted.lock().unwrap().insert(user);
+                let _ = sink.outputs.send(format!(
+                    "You have reached the end of the line. Join the others in {} for the final battle!",
+                    boss.channel.mention(),
+                ));
+                if boss.events.send(BossEvent::Join { user, name, state }).is_err() {
+                    log::error!("Failed to promote {} because the boss game has stopped", user);
+                }
+                // The player lives on in the boss game, so there's nothing left worth loading
+                if let Some(path) = &save {
+                    if let Err(e) = fs::remove_file(path) {
+                        log::error!("Failed to remove save file {:?}: {:?}", path, e);
+                    }
+                }
+                return
+            }
+        }
+        save_game(&mut game, save.as_deref());
+
+        match inputs.recv() {
+            Ok(input) => game.player_input(player, &input),
+            Err(_) => return,
+        }
+    }
+}
+
+/// Game loop for the boss channel, shared by every promoted player.
+fn run_boss_game(
+    events: mpsc::Receiver<BossEvent>,
+    outputs: async_mpsc::UnboundedSender<String>,
+    promoted: Arc<Mutex<HashSet<UserId>>>,
+    save: Option<PathBuf>,
+) {
+    let mut game = open_game(save.as_deref());
+    let mut sink = ChannelSink::new(outputs);
+
+    // Everyone promoted before a restart is still here, and plays on as soon as they speak up
+    for account in game.accounts() {
+        if let Some(user) = account_user(&account) {
+            promoted.lock().unwrap().insert(user);
+            if let Err(e) = game.login(&account) {
+                log::error!("Failed to log {} back in: {}", user, e);
+            }
+        }
+    }
+
+    while let Ok(event) = events.recv() {
+        let mut event = Some(event);
+        while let Some(e) = event {
+            match e {
+                BossEvent::Join { user, name, state } => {
+                    match game.add_player(&user_account(user), &state) {
+                        Ok(player) => {
+                            game.set_player_name(player, &name);
+                            sink.names.insert(player, name);
+                            game.player_input(player, "look");
+                        }
+                        Err(e) => log::error!("Failed to promote {}: {}", user, e),
+                    }
+                }
+                BossEvent::Input { user, name, text } => {
+                    if let Some(player) = game.account_player(&user_account(user)) {
+                        sink.names.entry(player).or_insert(name);
+                        game.player_input(player, &text);
+                    }
+                }
+            }
+            event = events.try_recv().ok();
+        }
+
+        game.run_until_idle();
+        game.flush_output(&mut sink);
+        if sink.closed {
+            return
+        }
+        save_game(&mut game, save.as_deref());
+    }
+}
+
+/// Account for the player belonging to a channel.
+fn channel_account(channel: ChannelId) -> String {
+    format!("discord-channel-{}", channel)
+}
+
+/// Account for a Discord user.
+fn user_account(user: UserId) -> String {
+    format!("discord-user-{}", user)
+}
+
+/// The Discord user an account belongs to, if it belongs to one.
+fn account_user(account: &str) -> Option<UserId> {
+    account.strip_prefix("discord-user-")?.parse().ok().map(UserId)
+}
+
+/// Split text into chunks Discord will accept, breaking on line boundaries where possible.
+fn split_message(text: &str) -> Vec<String> {
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for line in text.lines() {
+        let mut line: Vec<char> = line.chars().collect();
+        while line.len() > MAX_MESSAGE_LENGTH {
+            let rest = line.split_off(MAX_MESSAGE_LENGTH);
+            if !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+            }
+            chunks.push(line.into_iter().collect());
+            line = rest;
+        }
+
+        let line: String = line.into_iter().collect();
+        if current.chars().count() + line.chars().count() + 1 > MAX_MESSAGE_LENGTH {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(&line);
+    }
+
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+    chunks.retain(|chunk| !chunk.trim().is_empty());
+    chunks
+}
@@ -0,0 +1,204 @@
+// Accounts and sessions.
+//
+// Every player entity belongs to an account, named after whatever identity the player connects
+// with (a Discord user, a telnet login, ...). Logging in to an account resumes its character, or
+// creates one the first time. Logging out parks the character: it is taken out of its room, so
+// the rest of the world can't see or touch it, until the account logs in again.
+// ----------------------------
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use specs::error::NoError;
+use specs::prelude::*;
+use specs::saveload::{ConvertSaveload, Marker};
+use crate::game::components;
+use crate::game::map::RoomId;
+
+
+// What every new character starts out with.
+const PLAYER_HEALTH: i32 = 30;
+const PLAYER_ATTACK: i32 = 5;
+const PLAYER_DEFENSE: i32 = 2;
+const PLAYER_STEALTH: i32 = 3;
+
+
+/// Links accounts to their characters, and tracks which accounts are logged in.
+#[derive(Default, Debug)]
+pub struct Accounts {
+    characters: HashMap<String, Entity>,
+    online: HashSet<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LoginError {
+    // Somebody is already logged in to the account.
+    AlreadyConnected,
+}
+
+impl fmt::Display for LoginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoginError::AlreadyConnected => write!(f, "Someone is already playing as that character."),
+        }
+    }
+}
+
+impl Accounts {
+    pub fn new() -> Self {
+        Accounts {
+            characters: HashMap::new(),
+            online: HashSet::new(),
+        }
+    }
+
+    /// Get the character belonging to an account.
+    pub fn character(&self, account: &str) -> Option<Entity> {
+        self.characters.get(account).copied()
+    }
+
+    pub fn is_online(&self, account: &str) -> bool {
+        self.online.contains(account)
+    }
+
+    /// Link a new character to an account and mark the account as logged in.
+    pub fn register(&mut self, account: &str, character: Entity) -> Result<(), LoginError> {
+        if self.is_online(account) {
+            return Err(LoginError::AlreadyConnected)
+        }
+        self.characters.insert(account.to_string(), character);
+        self.online.insert(account.to_string());
+        Ok(())
+    }
+
+    /// Forget the account a character belongs to.
+    pub fn unregister(&mut self, character: Entity) {
+        let accounts: Vec<String> = self.characters.iter()
+            .filter(|(_, c)| **c == character)
+            .map(|(account, _)| account.clone())
+            .collect();
+        for account in accounts {
+            self.characters.remove(&account);
+            self.online.remove(&account);
+        }
+    }
+
+    /// Get every account which has a character.
+    pub fn accounts(&self) -> Vec<String> {
+        self.characters.keys().cloned().collect()
+    }
+}
+
+/// Saved accounts, linked to the markers of their characters.
+///
+/// Sessions aren't saved: everyone is logged out when a world is loaded.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AccountsData<M> {
+    characters: Vec<(String, M)>,
+}
+
+impl<M> ConvertSaveload<M> for Accounts
+where
+    M: Marker + Serialize + DeserializeOwned,
+{
+    type Data = AccountsData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        let characters = self.characters.iter()
+            .filter_map(|(account, character)| ids(*character).map(|id| (account.clone(), id)))
+            .collect();
+        Ok(AccountsData { characters })
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        let characters = data.characters.into_iter()
+            .filter_map(|(account, id)| ids(id).map(|character| (account, character)))
+            .collect();
+        Ok(Accounts { characters, online: HashSet::new() })
+    }
+}
+
+/// Log in to an account, resuming its character if it has one.
+///
+/// New characters are created in the `spawn` room.
+pub fn login(account: &str, spawn: RoomId, ecs: &mut World) -> Result<Entity, LoginError> {
+    if ecs.fetch::<Accounts>().is_online(account) {
+        return Err(LoginError::AlreadyConnected)
+    }
+
+    let existing = ecs.fetch::<Accounts>().character(account)
+        .filter(|character| ecs.entities().is_alive(*character));
+    match existing {
+        Some(character) => {
+            unpark(character, ecs);
+            ecs.write_resource::<Accounts>().online.insert(account.to_string());
+            Ok(character)
+        }
+        None => {
+            let character = create_character(spawn, ecs);
+            ecs.write_resource::<Accounts>().register(account, character)?;
+            Ok(character)
+        }
+    }
+}
+
+/// Log out of an account, parking its character until the account logs in again.
+pub fn logout(account: &str, ecs: &mut World) {
+    let character = {
+        let mut accounts = ecs.write_resource::<Accounts>();
+        if !accounts.online.remove(account) {
+            return
+        }
+        accounts.character(account)
+    };
+
+    if let Some(character) = character {
+        park(character, ecs);
+    }
+}
+
+/// Create a new player character in a room.
+pub fn create_character(room: RoomId, ecs: &mut World) -> Entity {
+    let mut journal = components::Journal::default();
+    journal.visited.insert(room);
+    ecs.create_entity()
+        .with(components::Player{})
+        .with(components::InRoom { room })
+        .with(components::Storage::new())
+        .with(components::Equipped::new())
+        .with(components::ActionQueue::new())
+        .with(components::Health::new(PLAYER_HEALTH))
+        .with(components::CombatStats { attack: PLAYER_ATTACK, defense: PLAYER_DEFENSE })
+        .with(components::Stealth { skill: PLAYER_STEALTH })
+        .with(journal)
+        .build()
+}
+
+/// Take a character out of its room until it is unparked.
+pub fn park(character: Entity, ecs: &mut World) {
+    let mut inrooms = ecs.write_storage::<components::InRoom>();
+    if let Some(inroom) = inrooms.remove(character) {
+        ecs.write_storage::<components::Parked>()
+            .insert(character, components::Parked { room: inroom.room })
+            .expect("Failed to insert Parked component");
+    }
+    if let Some(queue) = ecs.write_storage::<components::ActionQueue>().get_mut(character) {
+        queue.actions.clear();
+    }
+    ecs.write_storage::<components::Conversation>().remove(character);
+}
+
+fn unpark(character: Entity, ecs: &mut World) {
+    if let Some(parked) = ecs.write_storage::<components::Parked>().remove(character) {
+        ecs.write_storage::<components::InRoom>()
+            .insert(character, components::InRoom { room: parked.room })
+            .expect("Failed to insert InRoom component");
+    }
+}
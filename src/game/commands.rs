@@ -0,0 +1,185 @@
+// Commands players can type.
+//
+// Every verb is a `Command`, which describes how it is typed with syntax patterns like
+// `get <item> from <container>`. The first word of a pattern is the verb (which can also be
+// typed as any of the command's aliases), `<name>` captures whatever the player typed there as
+// an argument (punctuation straight after it, like `<target>,`, has to be typed too), and `[word]`
+// is a word the player can leave out. Commands are bundled up into cogs
+// (see `crate::cogs`) and registered with a game's `Commands`, which the input loop dispatches
+// through.
+// ----------------------------
+use std::collections::HashMap;
+
+use regex::Regex;
+use specs::prelude::*;
+
+use crate::cogs::Cog;
+use crate::game::{Message, Output, RunState};
+use crate::game::components::{Description, StorageAccess, StorageAction};
+use crate::game::targeting::{self, Target};
+
+
+/// A verb players can type.
+pub trait Command {
+    /// The verb, as shown in help.
+    fn name(&self) -> &'static str;
+
+    /// Other ways of typing the verb.
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Patterns the command can be typed as, tried in order.
+    fn syntax(&self) -> &'static [&'static str];
+
+    /// What the command does, in a sentence.
+    fn help(&self) -> &'static str;
+
+    /// Some ways the command might be typed, for its help page.
+    fn examples(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Carry out the command, returning `RunState::PlayerTurn` if it used up the player's turn.
+    fn run(&self, ctx: &Context, args: &Args) -> RunState;
+}
+
+/// Everything a command has to work with.
+pub struct Context<'a> {
+    pub world: &'a World,
+    pub player: Entity,
+    pub commands: &'a Commands,
+}
+
+/// The arguments captured by a command's syntax pattern.
+#[derive(Debug, Default)]
+pub struct Args {
+    args: HashMap<String, String>,
+}
+
+impl Args {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.args.get(name).map(|arg| arg.as_str())
+    }
+}
+
+struct Registered {
+    cog: &'static str,
+    command: Box<dyn Command>,
+    patterns: Vec<Regex>,
+}
+
+/// Every command a game understands.
+#[derive(Default)]
+pub struct Commands {
+    registered: Vec<Registered>,
+}
+
+impl Commands {
+    pub fn new() -> Self {
+        Commands { registered: Vec::new() }
+    }
+
+    /// Register every command in a cog.
+    pub fn register_cog(&mut self, cog: &dyn Cog) {
+        for command in cog.commands() {
+            self.register(cog.name(), command);
+        }
+    }
+
+    pub fn register(&mut self, cog: &'static str, command: Box<dyn Command>) {
+        let patterns = command.syntax().iter()
+            .map(|syntax| compile(syntax, command.aliases()))
+            .collect();
+        self.registered.push(Registered { cog, command, patterns });
+    }
+
+    /// Find the command a line of input is for, along with its arguments.
+    pub fn parse(&self, input: &str) -> Option<(&dyn Command, Args)> {
+        for registered in &self.registered {
+            for pattern in &registered.patterns {
+                if let Some(captures) = pattern.captures(input) {
+                    let args = pattern.capture_names()
+                        .flatten()
+                        .filter_map(|name| captures.name(name).map(|arg| (name.to_string(), arg.as_str().to_string())))
+                        .collect();
+                    return Some((registered.command.as_ref(), Args { args }))
+                }
+            }
+        }
+        None
+    }
+
+    /// Find a command by its name, or one of its aliases.
+    pub fn find(&self, name: &str) -> Option<&dyn Command> {
+        let name = name.to_lowercase();
+        self.registered.iter()
+            .map(|registered| registered.command.as_ref())
+            .find(|command| command.name() == name || command.aliases().contains(&name.as_str()))
+    }
+
+    /// Every registered command, along with the name of the cog it came from.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &dyn Command)> {
+        self.registered.iter().map(|registered| (registered.cog, registered.command.as_ref()))
+    }
+}
+
+/// Turn a syntax pattern into a regex matching the whole input.
+fn compile(syntax: &str, aliases: &[&str]) -> Regex {
+    let mut tokens = syntax.split_whitespace();
+    let verb = tokens.next().expect("Expected syntax to start with a verb");
+    let verbs = std::iter::once(verb)
+        .chain(aliases.iter().copied())
+        .map(regex::escape)
+        .collect::<Vec<String>>();
+
+    let mut pattern = format!(r"(?i)^\s*(?:{})", verbs.join("|"));
+    for token in tokens {
+        if let Some((name, rest)) = token.strip_prefix('<').and_then(|t| t.split_once('>')) {
+            pattern.push_str(&format!(r"\s+(?P<{}>.+?){}", name, regex::escape(rest)));
+        } else if let Some(word) = token.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            pattern.push_str(&format!(r"(?:\s+{})?", regex::escape(word)));
+        } else {
+            pattern.push_str(&format!(r"\s+{}", regex::escape(token)));
+        }
+    }
+    pattern.push_str(r"\s*$");
+
+    Regex::new(&pattern).unwrap_or_else(|e| panic!("Invalid command syntax {:?}: {}", syntax, e))
+}
+
+/// Work out which of the candidates a player means, telling them if it's none or unclear.
+pub fn find_target<'a, I>(player: Entity, phrase: &str, candidates: I, output: &mut Output, not_found: String) -> Option<Entity>
+where
+    I: IntoIterator<Item = (Entity, &'a Description)>,
+{
+    match targeting::resolve(phrase, candidates) {
+        Target::Found(entity) => Some(entity),
+        Target::NotFound => {
+            output.send(player, Message::error(not_found));
+            None
+        }
+        Target::Ambiguous(choices) => {
+            output.send(player, Message::error(format!("Which one did you mean? {}", choices.join(", "))));
+            None
+        }
+    }
+}
+
+/// Check the player may do something with a container, telling them why not if they can't.
+pub fn check_access(ctx: &Context, container: Entity, action: StorageAction, output: &mut Output) -> bool {
+    let accesses = ctx.world.read_storage::<StorageAccess>();
+    let access = match accesses.get(container) {
+        Some(access) => access,
+        None => return true,
+    };
+    let ds = ctx.world.read_storage::<Description>();
+    let glance = ds.get(container).map_or("container", |d| d.glance.as_str());
+    match access.check(ctx.player, action, glance) {
+        Ok(()) => true,
+        Err(refusal) => {
+            output.send(ctx.player, Message::error(refusal));
+            false
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use specs::{Component, Entity, VecStorage};
+use crate::game::map::ExitDirection;
+
+
+/// Phrases anyone might be greeted with, which always get an answer.
+const GREETINGS: [&str; 7] = ["hi", "hello", "hey", "greetings", "howdy", "hiya", "good day"];
+
+/// The node every conversation starts from.
+pub const START_NODE: &str = "start";
+
+/// What an NPC says when spoken to.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default)]
+#[storage(VecStorage)]
+#[serde(default)]
+pub struct Dialogue {
+    // Said back to greetings like "hi" or "hello".
+    pub greeting: Option<String>,
+    // Responses to anything mentioning one of their keywords, tried in order.
+    pub rules: Vec<DialogueRule>,
+    // Said when nothing else fits.
+    pub fallback: Option<String>,
+    // A conversation tree players can go through with `talk`, starting from the "start" node.
+    pub conversation: BTreeMap<String, DialogueNode>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DialogueRule {
+    pub keywords: Vec<String>,
+    pub response: String,
+}
+
+/// One step of a conversation: what the NPC says, and the answers players can pick from.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DialogueNode {
+    pub text: String,
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+}
+
+/// An answer players can give, when they meet its conditions.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DialogueChoice {
+    pub text: String,
+    // The node the conversation moves on to. Without one, picking the choice ends the conversation.
+    #[serde(default)]
+    pub goto: Option<String>,
+    #[serde(default)]
+    pub requires: Vec<Condition>,
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+}
+
+/// Something about a player which decides whether a choice is offered to them.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Condition {
+    // Carrying something with this glance or name.
+    HasItem(String),
+    // Having been in the room with this id.
+    Visited(String),
+    // Having had a flag set.
+    Flag(String),
+    // Having reached at least this stage of a quest.
+    QuestStage(String, u32),
+    Not(Box<Condition>),
+}
+
+/// What happens to the world when a player picks a choice.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Effect {
+    // Hand the player a new entity spawned from a prefab.
+    GiveItem(String),
+    // Unlock the exit out of the room with this id.
+    Unlock(String, ExitDirection),
+    SetFlag(String),
+    ClearFlag(String),
+    SetQuestStage(String, u32),
+}
+
+/// A player in the middle of a conversation tree, whose input answers the NPC until it ends.
+///
+/// Conversations aren't saved: a player in one picks up as usual after a load.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct Conversation {
+    pub with: Entity,
+    pub node: String,
+}
+
+impl Dialogue {
+    /// Find the response to something said to the NPC, if it has one.
+    pub fn respond(&self, message: &str) -> Option<&str> {
+        let message = normalize(message);
+        self.rules.iter()
+            .find(|rule| rule.keywords.iter().any(|keyword| mentions(&message, keyword)))
+            .map(|rule| rule.response.as_str())
+            .or_else(|| self.greeting.as_deref().filter(|_| is_greeting(&message)))
+            .or(self.fallback.as_deref())
+    }
+
+    /// Check whether the NPC has a conversation tree to go through.
+    pub fn converses(&self) -> bool {
+        self.conversation.contains_key(START_NODE)
+    }
+}
+
+/// Check whether a message greets whoever it's said to.
+pub fn is_greeting(message: &str) -> bool {
+    let message = normalize(message);
+    GREETINGS.iter().any(|greeting| mentions(&message, greeting))
+}
+
+/// Lowercase a message and strip its punctuation, leaving words separated by single spaces.
+fn normalize(message: &str) -> String {
+    message.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Check whether a normalized message contains a keyword, as whole words.
+fn mentions(message: &str, keyword: &str) -> bool {
+    let keyword = normalize(keyword);
+    !keyword.is_empty() && format!(" {} ", message).contains(&format!(" {} ", keyword))
+}
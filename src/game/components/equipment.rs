@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use specs::{Component, Entity, ReadStorage, VecStorage};
+use specs::error::NoError;
+use specs::saveload::{ConvertSaveload, Marker};
+
+use super::CombatStats;
+
+
+/// Where on its wearer something is worn or held.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Slot {
+    Head,
+    Body,
+    Hands,
+    Feet,
+    MainHand,
+    OffHand,
+}
+
+impl Slot {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Slot::Head => "head",
+            Slot::Body => "body",
+            Slot::Hands => "hands",
+            Slot::Feet => "feet",
+            Slot::MainHand => "main hand",
+            Slot::OffHand => "off hand",
+        }
+    }
+
+    /// Whether things in the slot are wielded, rather than worn.
+    pub fn is_held(&self) -> bool {
+        matches!(self, Slot::MainHand | Slot::OffHand)
+    }
+}
+
+/// Something which can be worn or wielded, and what it adds to its wearer's combat stats.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[storage(VecStorage)]
+pub struct Equippable {
+    pub slot: Slot,
+    #[serde(default)]
+    pub attack: i32,
+    #[serde(default)]
+    pub defense: i32,
+}
+
+/// Everything an entity is wearing or wielding, by slot. Equipped items are out of the wearer's
+/// `Storage` until they are taken off again.
+#[derive(Component, Default, Debug)]
+#[storage(VecStorage)]
+pub struct Equipped {
+    pub slots: BTreeMap<Slot, Entity>,
+}
+
+impl Equipped {
+    pub fn new() -> Self {
+        Equipped { slots: BTreeMap::new() }
+    }
+
+    /// Find the slot an item is equipped in.
+    pub fn slot_of(&self, item: Entity) -> Option<Slot> {
+        self.slots.iter().find(|(_, e)| **e == item).map(|(slot, _)| *slot)
+    }
+}
+
+/// An entity's combat stats, along with whatever its equipment adds to them.
+pub fn effective_stats(
+    entity: Entity,
+    stats: &ReadStorage<CombatStats>,
+    equipped: &ReadStorage<Equipped>,
+    equippables: &ReadStorage<Equippable>,
+) -> CombatStats {
+    let mut total = stats.get(entity).cloned().unwrap_or(CombatStats { attack: 0, defense: 0 });
+    let items = equipped.get(entity).map(|equipped| equipped.slots.values()).into_iter().flatten();
+    for equippable in items.filter_map(|item| equippables.get(*item)) {
+        total.attack += equippable.attack;
+        total.defense += equippable.defense;
+    }
+    total
+}
+
+/// A saved `Equipped`, holding the markers of its items instead of the entities.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EquippedData<M> {
+    slots: Vec<(Slot, M)>,
+}
+
+impl<M> ConvertSaveload<M> for Equipped
+where
+    M: Marker + Serialize + DeserializeOwned,
+{
+    type Data = EquippedData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        let slots = self.slots.iter()
+            .filter_map(|(slot, item)| ids(*item).map(|id| (*slot, id)))
+            .collect();
+        Ok(EquippedData { slots })
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        let slots = data.slots.into_iter()
+            .filter_map(|(slot, id)| ids(id).map(|item| (slot, item)))
+            .collect();
+        Ok(Equipped { slots })
+    }
+}
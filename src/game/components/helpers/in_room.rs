@@ -1,14 +1,11 @@
 use specs::prelude::*;
 use crate::game::map;
-use super::super::InRoom;
+use super::super::{Description, DisplayName, InRoom};
 
 pub fn get_entity_room_id(entity: Entity, ecs: &World) -> Option<map::RoomId> {
     let inrooms = ecs.read_storage::<InRoom>();
 
-    match inrooms.get(entity) {
-        Some(c) => Some(c.room),
-        None => None,
-    }
+    inrooms.get(entity).map(|c| c.room)
 }
 
 pub fn get_room_entities_as_bitset(room_id: map::RoomId, ecs: &World) -> BitSet {
@@ -21,5 +18,16 @@ pub fn get_room_entities_as_bitset(room_id: map::RoomId, ecs: &World) -> BitSet
         }
     }
 
-    return bitset;
+    bitset
+}
+
+/// Every player in a room but one, described by their names so they can be targeted.
+pub fn get_room_players_as_descriptions(room_id: map::RoomId, except: Entity, ecs: &World) -> Vec<(Entity, Description)> {
+    let inrooms = ecs.read_storage::<InRoom>();
+    let names = ecs.read_storage::<DisplayName>();
+
+    (&ecs.entities(), &inrooms, &names).join()
+        .filter(|(entity, inroom, _)| *entity != except && inroom.room == room_id)
+        .map(|(entity, _, name)| (entity, name.description()))
+        .collect()
 }
@@ -4,6 +4,7 @@ mod storage;
 pub use in_room::{
     get_entity_room_id,
     get_room_entities_as_bitset,
+    get_room_players_as_descriptions,
 };
 pub use storage::{
     get_entities_in_storage_as_bitset,
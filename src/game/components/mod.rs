@@ -1,14 +1,22 @@
+mod dialogue;
+mod equipment;
 mod storage;
 pub mod helpers;
 
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::string::String;
+use serde::{Deserialize, Serialize};
 use specs::{Component, VecStorage, Entity};
 use crate::game::map;
 
-pub use storage::Storage;
+pub use dialogue::{
+    is_greeting, Condition, Conversation, Dialogue, DialogueChoice, DialogueNode, Effect, START_NODE,
+};
+pub use equipment::{effective_stats, Equippable, Equipped, Slot};
+pub use storage::{Storage, StorageAccess, StorageAction};
 
 
-#[derive(Component, Debug)]
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
 #[storage(VecStorage)]
 pub struct Description {
     // Full description of the entity.
@@ -19,29 +27,75 @@ pub struct Description {
     pub name: Option<String>,
 }
 
-impl Description {
-    pub fn new() -> Self {
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[storage(VecStorage)]
+pub struct InRoom {
+    pub room: map::RoomId,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[storage(VecStorage)]
+pub struct Player;
+
+/// The name other players know a player's character by: the name they logged in with, as typed,
+/// rather than the account key it was normalized to.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[storage(VecStorage)]
+pub struct DisplayName {
+    pub name: String,
+}
+
+impl DisplayName {
+    /// Describe a player by their name, so they can be targeted like anything else in a room.
+    pub fn description(&self) -> Description {
         Description {
-            description: "".into(),
-            glance: "".into(),
-            name: None,
+            glance: self.name.clone(),
+            description: format!("{}, a fellow passenger.", self.name),
+            name: Some(self.name.clone()),
         }
     }
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
 #[storage(VecStorage)]
-pub struct InRoom {
+pub struct Npc;
+
+/// A character whose player has logged out, remembering the room they left.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[storage(VecStorage)]
+pub struct Parked {
     pub room: map::RoomId,
 }
 
-#[derive(Component, Debug)]
+/// What a player has done so far, which conversations can check and change.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default)]
 #[storage(VecStorage)]
-pub struct Player;
+#[serde(default)]
+pub struct Journal {
+    pub visited: BTreeSet<map::RoomId>,
+    pub flags: BTreeSet<String>,
+    // The stage reached in each quest. Quests which haven't started are at stage 0.
+    pub quests: BTreeMap<String, u32>,
+}
 
-#[derive(Component, Debug)]
+impl Journal {
+    pub fn quest_stage(&self, quest: &str) -> u32 {
+        self.quests.get(quest).copied().unwrap_or(0)
+    }
+}
+
+/// Commands a player has sent which are waiting for their turn.
+#[derive(Component, Default, Debug)]
 #[storage(VecStorage)]
-pub struct Npc;
+pub struct ActionQueue {
+    pub actions: VecDeque<String>,
+}
+
+impl ActionQueue {
+    pub fn new() -> Self {
+        ActionQueue { actions: VecDeque::new() }
+    }
+}
 
 #[derive(Component, Debug)]
 #[storage(VecStorage)]
@@ -49,13 +103,166 @@ pub struct ApplyMove {
     pub room: map::RoomId,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
 #[storage(VecStorage)]
 pub struct Item;
 
+/// Something which can be used, on its own or on something else, and what that does.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default)]
+#[storage(VecStorage)]
+#[serde(default)]
+pub struct Usable {
+    // Told to the player whenever using it works.
+    pub message: Option<String>,
+    pub effects: Vec<UseEffect>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum UseEffect {
+    // Heal whatever it's used on, or otherwise whoever uses it.
+    Heal(i32),
+    // Unlock the exit it's used on, when it's one of the exit's keys.
+    Unlock,
+    // Show a new description of whatever it's used on, or otherwise of itself, which sticks.
+    Reveal(String),
+    // Turn into an entity spawned from a prefab, wherever it is.
+    Transform(String),
+    // Get used up.
+    Consume,
+}
+
+/// What something is being used on.
+#[derive(Clone, Debug)]
+pub enum UseTarget {
+    Entity(Entity),
+    Exit(map::ExitDirection),
+}
+
+/// Putting on or taking off something, waiting for the equipment system to resolve it.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct ApplyEquipmentChange {
+    pub item: Entity,
+    pub equip: bool,
+}
+
+/// Using something, waiting for the use system to resolve it.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct WantsToUse {
+    pub item: Entity,
+    pub target: Option<UseTarget>,
+}
+
 #[derive(Component, Debug)]
 #[storage(VecStorage)]
 pub struct ApplyInventoryChange {
     pub from_container: Option<Entity>,
     pub to_container: Option<Entity>,
+    // Whoever is moving the item, who is held to the containers' access rules.
+    pub by: Entity,
+    // Whether the item is being stolen, slipping past the access rules unnoticed.
+    pub stolen: bool,
+}
+
+/// How much damage an entity can take, out of how much it can take when unhurt.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[storage(VecStorage)]
+pub struct Health {
+    pub current: i32,
+    pub max: i32,
+}
+
+impl Health {
+    pub fn new(max: i32) -> Self {
+        Health { current: max, max }
+    }
+}
+
+/// How hard an entity hits, and how much of every hit it takes shrugs off.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[storage(VecStorage)]
+pub struct CombatStats {
+    pub attack: i32,
+    pub defense: i32,
+}
+
+/// The remains of something which died, holding whatever it carried.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[storage(VecStorage)]
+pub struct Corpse;
+
+/// What an NPC gets up to on its own, to make the rooms it's in feel alive.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[storage(VecStorage)]
+#[serde(default)]
+pub struct Behaviour {
+    // The chance of doing something every NPC turn, from 0 to 1.
+    pub chance: f64,
+    // Things the NPC might do, following its glance: "drunkenly sings a binary tune..".
+    pub emotes: Vec<String>,
+    // Whether the NPC might wander off through an open exit.
+    pub wanders: bool,
+    // Whether the NPC might open up a container in the room and look inside.
+    pub rummages: bool,
+}
+
+impl Default for Behaviour {
+    fn default() -> Self {
+        Behaviour { chance: 0.2, emotes: Vec::new(), wanders: false, rummages: false }
+    }
+}
+
+/// The loot table rolled for the contents of an entity's corpse.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[storage(VecStorage)]
+pub struct DropsLoot {
+    pub table: String,
+}
+
+/// An attack on another entity, waiting for the combat system to resolve it.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct WantsToAttack {
+    pub target: Entity,
+}
+
+/// How good an entity is at going unnoticed, like slipping a hand into someone's pocket.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[storage(VecStorage)]
+pub struct Stealth {
+    pub skill: i32,
+}
+
+/// How likely an NPC is to notice a thief.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[storage(VecStorage)]
+pub struct Awareness {
+    pub level: i32,
+}
+
+/// An attempt to steal from an NPC, waiting for the theft system to resolve it. Without an item,
+/// the thief takes whatever they can grab.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct WantsToSteal {
+    pub from: Entity,
+    pub item: Option<Entity>,
+}
+
+/// An NPC keeping a close eye on its belongings after someone tried to steal them, for a number
+/// of NPC turns.
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[storage(VecStorage)]
+pub struct Wary {
+    pub turns: u32,
+}
+
+/// An NPC out to get someone, attacking them whenever they're in the same room.
+///
+/// Grudges aren't saved: NPCs calm down when a world is loaded.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub struct Hostile {
+    pub target: Entity,
 }
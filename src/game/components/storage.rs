@@ -1,4 +1,8 @@
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
 use specs::{Component, Entity, VecStorage};
+use specs::error::NoError;
+use specs::saveload::{ConvertSaveload, Marker};
 
 
 #[derive(Component, Default, Debug)]
@@ -12,3 +16,112 @@ impl Storage {
         Storage { items: Vec::new() }
     }
 }
+
+/// A saved `Storage`, holding the markers of its items instead of the entities.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageData<M> {
+    items: Vec<M>,
+}
+
+impl<M> ConvertSaveload<M> for Storage
+where
+    M: Marker + Serialize + DeserializeOwned,
+{
+    type Data = StorageData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        Ok(StorageData { items: self.items.iter().filter_map(|item| ids(*item)).collect() })
+    }
+
+    fn convert_from<F>(data: Self::Data, ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        Ok(Storage { items: data.items.into_iter().filter_map(ids).collect() })
+    }
+}
+
+/// Rules on who can do what with a container. Containers without any are open to everyone.
+#[derive(Component, Clone, Debug, Default)]
+#[storage(VecStorage)]
+pub struct StorageAccess {
+    // Things can be taken out, but not put in.
+    pub remove_only: bool,
+    // Things can be put in, but not taken out.
+    pub insert_only: bool,
+    // Nobody can see what's inside.
+    pub hidden: bool,
+    // Nobody but the owner can touch the container at all, while the owner can do anything with it.
+    pub owner: Option<Entity>,
+}
+
+/// Something an entity can try to do with a container.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageAction {
+    Look,
+    Take,
+    Put,
+}
+
+impl StorageAccess {
+    /// Check whether an entity may do something with the container, described by its glance,
+    /// returning the reason to give them if they can't.
+    pub fn check(&self, who: Entity, action: StorageAction, container: &str) -> Result<(), String> {
+        match self.owner {
+            Some(owner) if owner == who => return Ok(()),
+            Some(_) => return Err(format!("The {} isn't yours to go through.", container)),
+            None => {}
+        }
+        match action {
+            StorageAction::Look if self.hidden => Err(format!("You can't see inside the {}.", container)),
+            StorageAction::Take if self.insert_only => Err(format!("Things can only be put in the {}, not taken out.", container)),
+            StorageAction::Put if self.remove_only => Err(format!("Things can only be taken out of the {}, not put in.", container)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A saved `StorageAccess`, holding the marker of its owner instead of the entity.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StorageAccessData<M> {
+    remove_only: bool,
+    insert_only: bool,
+    hidden: bool,
+    owner: Option<M>,
+}
+
+impl<M> ConvertSaveload<M> for StorageAccess
+where
+    M: Marker + Serialize + DeserializeOwned,
+{
+    type Data = StorageAccessData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        Ok(StorageAccessData {
+            remove_only: self.remove_only,
+            insert_only: self.insert_only,
+            hidden: self.hidden,
+            owner: self.owner.and_then(&mut ids),
+        })
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        Ok(StorageAccess {
+            remove_only: data.remove_only,
+            insert_only: data.insert_only,
+            hidden: data.hidden,
+            owner: data.owner.and_then(&mut ids),
+        })
+    }
+}
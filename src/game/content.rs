@@ -0,0 +1,606 @@
+// Maps described in content files.
+//
+// A map file lists the rooms of a map, the exits between them and the entities placed in each
+// room, so new train cars can be written without touching any code. Map files are written in RON:
+//
+//     #![enable(implicit_some)]
+//     (
+//         spawn: "atrium",
+//         rooms: [
+//             (
+//                 id: "atrium",
+//                 description: "A train car, transformed into a luxurious atrium.",
+//                 exits: [
+//                     (direction: Next, to: "lounge", description: "Hallway leading to the next train car"),
+//                 ],
+//                 contents: [
+//                     (glance: "bin", description: "A plastic bin.", contents: [
+//                         (id: "keycard", glance: "blue keycard", description: "A keycard.", item: true),
+//                     ]),
+//                 ],
+//             ),
+//         ],
+//     )
+//
+// Rooms are referred to by their `id`, and so are entities which other parts of the map need to
+// know about, like the keys to a locked gate. Exits lead `Next` or `Previous`, in a compass
+// direction (`North`, `South`, `East`, `West`), `Up`, `Down`, `In` or `Out`, or through something
+// named like `Named("hatch")`, which players take with "hatch", "go hatch" or "enter hatch".
+//
+// Exits can start out `closed` or `locked`, and any exit with `keys` can be locked and unlocked by
+// players carrying one of them. A `two_sided` exit is the same gate as the exit leading back (or
+// the one named by `back`, when the other room has more than one), so it opens and locks on both
+// sides at once, and an exit which `relocks` shuts and locks itself behind whoever goes through.
+// Entities with `contents` get a `Storage` holding them.
+//
+// Entities which show up more than once are better written as prefabs, kept in a prefab file
+// mapping prefab names to entities:
+//
+//     #![enable(implicit_some)]
+//     {
+//         "robot_pirate": (
+//             name: ["1-L3GG3D-J03", "2-F1NG3RD-B0B", "B1RD-3Y3-B1LLY"],
+//             glance: "robot pirate",
+//             description: "A decommissioned kitchen droid equipped with a buccaneer's hat",
+//             npc: true,
+//         ),
+//     }
+//
+// A map places a prefab with `(prefab: "robot_pirate")`, overriding any of its fields as needed.
+// Text fields given as a list pick one of the choices at random every time the entity spawns.
+// Entities with `loot` roll on that loot table for more contents, and foes with `health` and a
+// `difficulty` leave behind the loot for their difficulty when they die (see `crate::game::loot`).
+//
+// NPCs liven up their rooms with a `behaviour`, like `(emotes: ["hums a tune"], wanders: true)`,
+// picking something to do now and then: one of their emotes, wandering off through an open exit,
+// or rummaging through a container (with `rummages: true`). They answer players who talk to them
+// with their `dialogue`: a `greeting`, `rules` giving the response to messages mentioning any of
+// their `keywords`, and a `fallback` for everything else.
+//
+// A dialogue's `conversation` is a tree of nodes, keyed by id and starting from "start", which
+// players go through with `talk`. Each node has the NPC's `text` and the `choices` players answer
+// with: the node each `goto`es (or none, to end the conversation), the conditions it `requires`
+// (`HasItem("blue keycard")`, `Visited("roof")`, `Flag("met_clarice")`,
+// `QuestStage("lost_keycard", 1)` or `Not(...)` any of these) and its `effects` (`GiveItem(prefab)`,
+// `Unlock("jazz_club", Next)`, `SetFlag(...)`, `ClearFlag(...)` or `SetQuestStage(...)`).
+//
+// Things players can `use` are `usable`, with a `message` shown when using them works and their
+// `effects`: `Heal(10)`, `Unlock` (the exit it's used on, when it's one of the exit's keys),
+// `Reveal("...")` (a new description for whatever it's used on, or itself), `Transform(prefab)`
+// or `Consume`.
+//
+// Things which can be worn or wielded are `equippable`, like `(slot: MainHand, attack: 2)`, in
+// one of the slots `Head`, `Body`, `Hands`, `Feet`, `MainHand` or `OffHand`, adding their
+// `attack` and `defense` to the wearer's. Entities start out wearing whatever they're `equipped`
+// with.
+//
+// Entities' contents can be restricted with `access: (remove_only: true)`, and likewise with
+// `insert_only`, `hidden` (nobody can look inside) or `owner_only` (nobody but the entity itself
+// can get at them). Players can still try to steal from NPCs, who catch them more often the
+// higher their `awareness`.
+// ----------------------------
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use specs::prelude::*;
+
+use crate::game::components;
+use crate::game::loot::{self, LootTables};
+use crate::game::map::{ExitDirection, Gate, Map, RoomId};
+
+
+/// The map every game starts on, unless another map file is given.
+pub const DEFAULT_MAP: &str = include_str!("../../maps/default.ron");
+
+/// The prefabs every game can spawn, unless another prefab file is given.
+pub const DEFAULT_PREFABS: &str = include_str!("../../prefabs/default.ron");
+
+#[derive(Deserialize, Debug)]
+pub struct MapFile {
+    pub spawn: String,
+    pub rooms: Vec<RoomFile>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RoomFile {
+    pub id: String,
+    pub description: String,
+    #[serde(default)]
+    pub exits: Vec<ExitFile>,
+    #[serde(default)]
+    pub contents: Vec<EntityFile>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ExitFile {
+    pub direction: ExitDirection,
+    pub to: String,
+    pub description: String,
+    #[serde(default)]
+    pub closed: bool,
+    // Locked gates start out closed, and need one of the keys to open.
+    #[serde(default)]
+    pub locked: bool,
+    // Ids of the entities which lock and unlock the gate. Gates without keys have no lock.
+    #[serde(default)]
+    pub keys: Vec<String>,
+    // Whether the gate shuts and locks itself again behind whoever goes through it.
+    #[serde(default)]
+    pub relocks: bool,
+    // Whether the exit back from the other room is the same gate, sharing its keys and state.
+    #[serde(default)]
+    pub two_sided: bool,
+    // Which exit in the other room is the way back, only needed when more than one leads back.
+    #[serde(default)]
+    pub back: Option<ExitDirection>,
+}
+
+/// An entity in a map or prefab file. Every field is optional, so entities based on a prefab
+/// only need to give the fields they change.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct EntityFile {
+    // Only needed when something else in the map refers to the entity.
+    pub id: Option<String>,
+    // Name of the prefab this entity is based on.
+    pub prefab: Option<String>,
+    pub name: Option<Text>,
+    pub glance: Option<Text>,
+    pub description: Option<Text>,
+    pub item: Option<bool>,
+    pub npc: Option<bool>,
+    // Entities with health can be attacked, and fight back with their attack and defense.
+    pub health: Option<i32>,
+    pub attack: Option<i32>,
+    pub defense: Option<i32>,
+    // Entities stored inside of this one.
+    pub contents: Option<Vec<EntityFile>>,
+    // Entities this one wears or wields, each in the slot it's equippable in.
+    pub equipped: Option<Vec<EntityFile>>,
+    // A loot table rolled for more contents, every time the entity spawns.
+    pub loot: Option<String>,
+    // The loot table rolled for the contents of the entity's corpse, if not the one for its difficulty.
+    pub drops: Option<String>,
+    pub difficulty: Option<u32>,
+    // How likely an NPC is to catch someone stealing from it.
+    pub awareness: Option<i32>,
+    // Rules on who can do what with the entity's contents.
+    pub access: Option<AccessFile>,
+    // What an NPC does on its own.
+    pub behaviour: Option<components::Behaviour>,
+    // What an NPC says when spoken to.
+    pub dialogue: Option<components::Dialogue>,
+    // What happens when the entity is used.
+    pub usable: Option<components::Usable>,
+    // Where the entity is worn or wielded, and what that adds to its wearer's stats.
+    pub equippable: Option<components::Equippable>,
+}
+
+/// Access rules for an entity's contents. Nothing is restricted unless it's turned on.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct AccessFile {
+    pub remove_only: bool,
+    pub insert_only: bool,
+    pub hidden: bool,
+    // Only the entity itself can get at its contents, like an NPC's pockets.
+    pub owner_only: bool,
+}
+
+/// Text which is either always the same, or picked at random from a list of choices.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Text {
+    Fixed(String),
+    OneOf(Vec<String>),
+}
+
+impl Text {
+    fn pick(&self) -> String {
+        match self {
+            Text::Fixed(text) => text.clone(),
+            Text::OneOf(choices) => choices.choose(&mut rand::thread_rng()).cloned().unwrap_or_default(),
+        }
+    }
+}
+
+/// Named entities which can be spawned any number of times.
+#[derive(Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct Prefabs(HashMap<String, EntityFile>);
+
+#[derive(Debug)]
+pub enum ContentError {
+    // The file couldn't be read.
+    Io(io::Error),
+
+    // The file isn't valid RON, or doesn't describe a map or prefabs.
+    Parse(ron::error::SpannedError),
+
+    // Something refers to a room which isn't in the map.
+    UnknownRoom(String),
+
+    // A gate's key refers to an entity which isn't in the map.
+    UnknownEntity(String),
+
+    // Two rooms, or two entities, share an id.
+    DuplicateId(String),
+
+    // An entity is based on a prefab which doesn't exist.
+    UnknownPrefab(String),
+
+    // A prefab is based on itself, directly or through other prefabs.
+    RecursivePrefab(String),
+
+    // An entity is missing a field it can't do without, even after applying its prefab.
+    MissingField(&'static str),
+
+    // A two-sided exit leads to a room with no exit back.
+    NoWayBack(String),
+
+    // A two-sided exit leads to a room with more than one exit back, without saying which it is.
+    AmbiguousWayBack(String),
+
+    // Something rolls on a loot table which doesn't exist.
+    UnknownLootTable(String),
+
+    // A loot table rolls on itself, directly or through other tables.
+    RecursiveLootTable(String),
+
+    // A conversation goes to a node which isn't in its tree.
+    UnknownDialogueNode(String),
+
+    // A conversation unlocks an exit a room doesn't have.
+    UnknownExit(String, ExitDirection),
+
+    // An entity is equipped with something which can't be worn or wielded.
+    NotEquippable(String),
+
+    // An entity is equipped with more than one thing in the same slot.
+    SlotTaken(components::Slot),
+}
+
+impl fmt::Display for ContentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentError::Io(e) => write!(f, "Failed to read content file: {}", e),
+            ContentError::Parse(e) => write!(f, "Invalid content file: {}", e),
+            ContentError::UnknownRoom(id) => write!(f, "No room has the id {:?}", id),
+            ContentError::UnknownEntity(id) => write!(f, "No entity has the id {:?}", id),
+            ContentError::DuplicateId(id) => write!(f, "The id {:?} is used more than once", id),
+            ContentError::UnknownPrefab(name) => write!(f, "No prefab is named {:?}", name),
+            ContentError::RecursivePrefab(name) => write!(f, "The prefab {:?} is based on itself", name),
+            ContentError::MissingField(field) => write!(f, "An entity has no {}", field),
+            ContentError::UnknownLootTable(name) => write!(f, "No loot table is named {:?}", name),
+            ContentError::RecursiveLootTable(name) => write!(f, "The loot table {:?} rolls on itself", name),
+            ContentError::NoWayBack(id) => write!(f, "The two-sided exit from {:?} has no exit leading back", id),
+            ContentError::AmbiguousWayBack(id) => {
+                write!(f, "The two-sided exit from {:?} has more than one exit leading back, and needs a `back`", id)
+            }
+            ContentError::UnknownDialogueNode(id) => write!(f, "No conversation node has the id {:?}", id),
+            ContentError::UnknownExit(room, dir) => write!(f, "The room {:?} has no {} exit", room, dir.name()),
+            ContentError::NotEquippable(glance) => write!(f, "The {:?} can't be worn or wielded", glance),
+            ContentError::SlotTaken(slot) => write!(f, "More than one thing is equipped in the {} slot", slot.name()),
+        }
+    }
+}
+
+impl From<io::Error> for ContentError {
+    fn from(e: io::Error) -> Self {
+        ContentError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for ContentError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        ContentError::Parse(e)
+    }
+}
+
+impl MapFile {
+    pub fn parse(text: &str) -> Result<Self, ContentError> {
+        Ok(ron::from_str(text)?)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, ContentError> {
+        MapFile::parse(&fs::read_to_string(path)?)
+    }
+}
+
+impl Prefabs {
+    pub fn parse(text: &str) -> Result<Self, ContentError> {
+        Ok(ron::from_str(text)?)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, ContentError> {
+        Prefabs::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Fill in the fields an entity doesn't give itself from the prefab it is based on, and that
+    /// prefab's own prefab, and so on.
+    fn resolve(&self, file: &EntityFile) -> Result<EntityFile, ContentError> {
+        let mut resolved = file.clone();
+        let mut seen: Vec<&str> = Vec::new();
+        let mut next = file.prefab.as_deref();
+        while let Some(name) = next {
+            if seen.contains(&name) {
+                return Err(ContentError::RecursivePrefab(name.to_string()))
+            }
+            seen.push(name);
+
+            let prefab = self.0.get(name).ok_or_else(|| ContentError::UnknownPrefab(name.to_string()))?;
+            resolved.name = resolved.name.or_else(|| prefab.name.clone());
+            resolved.glance = resolved.glance.or_else(|| prefab.glance.clone());
+            resolved.description = resolved.description.or_else(|| prefab.description.clone());
+            resolved.item = resolved.item.or(prefab.item);
+            resolved.npc = resolved.npc.or(prefab.npc);
+            resolved.health = resolved.health.or(prefab.health);
+            resolved.attack = resolved.attack.or(prefab.attack);
+            resolved.defense = resolved.defense.or(prefab.defense);
+            resolved.contents = resolved.contents.or_else(|| prefab.contents.clone());
+            resolved.equipped = resolved.equipped.or_else(|| prefab.equipped.clone());
+            resolved.loot = resolved.loot.or_else(|| prefab.loot.clone());
+            resolved.drops = resolved.drops.or_else(|| prefab.drops.clone());
+            resolved.difficulty = resolved.difficulty.or(prefab.difficulty);
+            resolved.awareness = resolved.awareness.or(prefab.awareness);
+            resolved.access = resolved.access.or_else(|| prefab.access.clone());
+            resolved.behaviour = resolved.behaviour.or_else(|| prefab.behaviour.clone());
+            resolved.dialogue = resolved.dialogue.or_else(|| prefab.dialogue.clone());
+            resolved.usable = resolved.usable.or_else(|| prefab.usable.clone());
+            resolved.equippable = resolved.equippable.or_else(|| prefab.equippable.clone());
+            next = prefab.prefab.as_deref();
+        }
+        Ok(resolved)
+    }
+}
+
+/// Build the map described by a map file, spawning its entities into the world.
+///
+/// The world's `Prefabs` are used for entities based on a prefab.
+pub fn build(file: &MapFile, ecs: &mut World) -> Result<Map, ContentError> {
+    let mut map = Map::new();
+
+    // Every room has to exist before exits can lead to them
+    let mut room_ids: HashMap<&str, RoomId> = HashMap::new();
+    for room_file in &file.rooms {
+        let room = map.create_room(&room_file.description);
+        if room_ids.insert(&room_file.id, room.id()).is_some() {
+            return Err(ContentError::DuplicateId(room_file.id.clone()))
+        }
+        map.name_room(&room_file.id, room.id());
+        map.rooms.insert(room.id(), room);
+    }
+    let room_id = |id: &str| room_ids.get(id).copied().ok_or_else(|| ContentError::UnknownRoom(id.to_string()));
+
+    let mut entity_ids: HashMap<String, Entity> = HashMap::new();
+    for room_file in &file.rooms {
+        let room = room_id(&room_file.id)?;
+        for entity_file in &room_file.contents {
+            let entity = spawn(entity_file, &mut entity_ids, ecs)?;
+            ecs.write_storage::<components::InRoom>()
+                .insert(entity, components::InRoom { room })
+                .expect("Failed to insert InRoom component");
+        }
+    }
+
+    for room_file in &file.rooms {
+        let room = room_id(&room_file.id)?;
+        for exit in &room_file.exits {
+            let keys = exit.keys.iter()
+                .map(|key| entity_ids.get(key).copied().ok_or_else(|| ContentError::UnknownEntity(key.clone())))
+                .collect::<Result<Vec<Entity>, ContentError>>()?;
+            let gate = build_gate(exit, room_id(&exit.to)?, &exit.description, keys);
+            map.room_mut(&room).add_exit(exit.direction.clone(), gate);
+        }
+    }
+
+    // The far side of a two-sided exit takes on the exit's lock and state, keeping its own
+    // description, and each side remembers which exit the other is
+    for room_file in &file.rooms {
+        let room = room_id(&room_file.id)?;
+        for exit in room_file.exits.iter().filter(|exit| exit.two_sided) {
+            let to = room_id(&exit.to)?;
+            let mut ways_back = map.room(&to).exits.iter()
+                .filter(|(dir, gate)| gate.to() == room && exit.back.as_ref().is_none_or(|back| back == *dir))
+                .map(|(dir, gate)| (dir.clone(), gate.description.clone()));
+            let (dir, description) = match (ways_back.next(), ways_back.next()) {
+                (Some(way_back), None) => way_back,
+                (Some(_), Some(_)) => return Err(ContentError::AmbiguousWayBack(room_file.id.clone())),
+                (None, _) => return Err(ContentError::NoWayBack(room_file.id.clone())),
+            };
+            let keys: Vec<Entity> = exit.keys.iter().map(|key| entity_ids[key]).collect();
+            let far_side = build_gate(exit, room, &description, keys.clone()).two_sided(exit.direction.clone());
+            let near_side = build_gate(exit, to, &exit.description, keys).two_sided(dir.clone());
+            map.room_mut(&to).add_exit(dir, far_side);
+            map.room_mut(&room).add_exit(exit.direction.clone(), near_side);
+        }
+    }
+
+    for dialogue in ecs.read_storage::<components::Dialogue>().join() {
+        check_conversation(dialogue, &map, &ecs.fetch::<Prefabs>())?;
+    }
+
+    map.set_spawn(room_id(&file.spawn)?);
+    Ok(map)
+}
+
+/// Check that a conversation only goes to nodes, rooms, exits and prefabs which exist.
+fn check_conversation(dialogue: &components::Dialogue, map: &Map, prefabs: &Prefabs) -> Result<(), ContentError> {
+    let nodes = &dialogue.conversation;
+    if !nodes.is_empty() && !dialogue.converses() {
+        return Err(ContentError::UnknownDialogueNode(components::START_NODE.to_string()))
+    }
+    for choice in nodes.values().flat_map(|node| &node.choices) {
+        if let Some(goto) = choice.goto.as_ref().filter(|goto| !nodes.contains_key(*goto)) {
+            return Err(ContentError::UnknownDialogueNode(goto.clone()))
+        }
+        let rooms = choice.requires.iter().filter_map(|condition| match condition {
+            components::Condition::Visited(room) => Some(room),
+            _ => None,
+        });
+        for room in rooms {
+            map.room_named(room).ok_or_else(|| ContentError::UnknownRoom(room.clone()))?;
+        }
+        for effect in &choice.effects {
+            match effect {
+                components::Effect::GiveItem(prefab) if !prefabs.0.contains_key(prefab) => {
+                    return Err(ContentError::UnknownPrefab(prefab.clone()))
+                }
+                components::Effect::Unlock(room, dir) => {
+                    let id = map.room_named(room).ok_or_else(|| ContentError::UnknownRoom(room.clone()))?;
+                    if !map.room(&id).exits.contains_key(dir) {
+                        return Err(ContentError::UnknownExit(room.clone(), dir.clone()))
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Build the gate an exit describes.
+fn build_gate(exit: &ExitFile, to: RoomId, description: &str, keys: Vec<Entity>) -> Gate {
+    let mut gate = if exit.locked {
+        Gate::new_locked(to, description, keys)
+    } else if !keys.is_empty() {
+        Gate::new_lockable(to, description, keys)
+    } else {
+        Gate::new(to, description)
+    };
+    if exit.closed {
+        gate = gate.closed();
+    }
+    if exit.relocks {
+        gate = gate.relocking();
+    }
+    gate
+}
+
+/// Spawn a prefab in a room.
+pub fn spawn_prefab(name: &str, room: RoomId, ecs: &mut World) -> Result<Entity, ContentError> {
+    let entity = spawn_detached(name, ecs)?;
+    ecs.write_storage::<components::InRoom>()
+        .insert(entity, components::InRoom { room })
+        .expect("Failed to insert InRoom component");
+    Ok(entity)
+}
+
+/// Spawn a prefab without putting it anywhere, ready to be stored in a container.
+pub fn spawn_detached(name: &str, ecs: &mut World) -> Result<Entity, ContentError> {
+    let file = EntityFile { prefab: Some(name.to_string()), ..EntityFile::default() };
+    spawn(&file, &mut HashMap::new(), ecs)
+}
+
+/// Spawn an entity, and everything stored inside of it.
+fn spawn(file: &EntityFile, ids: &mut HashMap<String, Entity>, ecs: &mut World) -> Result<Entity, ContentError> {
+    let file = ecs.fetch::<Prefabs>().resolve(file)?;
+    let glance = file.glance.as_ref().ok_or(ContentError::MissingField("glance"))?.pick();
+    let description = file.description.as_ref().ok_or(ContentError::MissingField("description"))?.pick();
+
+    let contents = match &file.contents {
+        Some(contents) => Some(
+            contents.iter()
+                .map(|content| spawn(content, ids, ecs))
+                .collect::<Result<Vec<Entity>, ContentError>>()?
+        ),
+        None => None,
+    };
+    let equipped = match &file.equipped {
+        Some(equipped) => {
+            let mut equipment = components::Equipped::new();
+            for item_file in equipped {
+                let item = spawn(item_file, ids, ecs)?;
+                let slot = ecs.read_storage::<components::Equippable>().get(item).map(|e| e.slot);
+                let slot = slot.ok_or_else(|| {
+                    let glance = ecs.read_storage::<components::Description>().get(item).unwrap().glance.clone();
+                    ContentError::NotEquippable(glance)
+                })?;
+                if equipment.slots.insert(slot, item).is_some() {
+                    return Err(ContentError::SlotTaken(slot))
+                }
+            }
+            Some(equipment)
+        }
+        None => None,
+    };
+
+    // Foes without a table of their own drop whatever is usual for their difficulty
+    let drops = match (&file.drops, file.difficulty) {
+        (Some(table), _) => Some(table.clone()),
+        (None, Some(difficulty)) => ecs.fetch::<LootTables>().for_difficulty(difficulty).map(str::to_string),
+        (None, None) => None,
+    };
+
+    let mut builder = ecs.create_entity()
+        .with(components::Description {
+            description,
+            glance,
+            name: file.name.as_ref().map(Text::pick),
+        });
+    if file.item.unwrap_or(false) {
+        builder = builder.with(components::Item {});
+    }
+    if file.npc.unwrap_or(false) {
+        builder = builder.with(components::Npc {});
+    }
+    if let Some(health) = file.health {
+        builder = builder.with(components::Health::new(health));
+    }
+    if file.attack.is_some() || file.defense.is_some() {
+        builder = builder.with(components::CombatStats {
+            attack: file.attack.unwrap_or(0),
+            defense: file.defense.unwrap_or(0),
+        });
+    }
+    if let Some(items) = contents {
+        builder = builder.with(components::Storage { items });
+    }
+    if let Some(table) = drops {
+        builder = builder.with(components::DropsLoot { table });
+    }
+    if let Some(level) = file.awareness {
+        builder = builder.with(components::Awareness { level });
+    }
+    if let Some(behaviour) = &file.behaviour {
+        builder = builder.with(behaviour.clone());
+    }
+    if let Some(dialogue) = &file.dialogue {
+        builder = builder.with(dialogue.clone());
+    }
+    if let Some(usable) = &file.usable {
+        builder = builder.with(usable.clone());
+    }
+    if let Some(equippable) = &file.equippable {
+        builder = builder.with(equippable.clone());
+    }
+    if let Some(equipment) = equipped {
+        builder = builder.with(equipment);
+    }
+    let entity = builder.build();
+    if let Some(access) = &file.access {
+        ecs.write_storage::<components::StorageAccess>()
+            .insert(entity, components::StorageAccess {
+                remove_only: access.remove_only,
+                insert_only: access.insert_only,
+                hidden: access.hidden,
+                owner: access.owner_only.then_some(entity),
+            })
+            .expect("Failed to insert StorageAccess component");
+    }
+    if let Some(table) = &file.loot {
+        loot::fill(table, entity, ecs)?;
+    }
+
+    if let Some(id) = &file.id {
+        if ids.insert(id.clone(), entity).is_some() {
+            return Err(ContentError::DuplicateId(id.clone()))
+        }
+    }
+    Ok(entity)
+}
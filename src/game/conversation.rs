@@ -0,0 +1,214 @@
+// Conversation trees.
+//
+// Talking to an NPC with a conversation tree puts the player in a conversation, which takes over
+// their input: until it ends, they answer the NPC by the number of one of the choices offered,
+// or say "bye" to walk away. Choices are only offered to players who meet their conditions, and
+// picking one applies its effects before the conversation moves on.
+// ----------------------------
+use specs::prelude::*;
+
+use crate::game::{Message, Output, RunState};
+use crate::game::components::{self, Condition, DialogueNode, Effect};
+use crate::game::content;
+use crate::game::map::Map;
+
+
+/// What players type to walk away from a conversation.
+const GOODBYES: [&str; 2] = ["bye", "goodbye"];
+
+/// Start a conversation between a player and an NPC, from the first node of its tree.
+pub fn start(world: &World, player: Entity, npc: Entity) {
+    world.write_storage::<components::Conversation>()
+        .insert(player, components::Conversation { with: npc, node: components::START_NODE.to_string() })
+        .expect("Failed to insert Conversation component");
+    show(world, player, npc, components::START_NODE);
+}
+
+/// Answer the NPC a player is talking to.
+///
+/// Returns `None` when the player isn't in a conversation, or the NPC has gone, so the input
+/// should be handled as a command instead.
+pub fn answer(world: &World, player: Entity, input: &str) -> Option<RunState> {
+    let (npc, node_id) = {
+        let conversations = world.read_storage::<components::Conversation>();
+        let conversation = conversations.get(player)?;
+        (conversation.with, conversation.node.clone())
+    };
+    let glance = glance(world, npc);
+    if !is_around(world, player, npc) {
+        end(world, player);
+        let gone = format!("The {} is no longer around to talk to.", glance);
+        world.write_resource::<Output>().send(player, gone);
+        return None
+    }
+    if GOODBYES.contains(&input.to_lowercase().as_str()) {
+        end(world, player);
+        world.write_resource::<Output>().send(player, format!("You stop talking to the {}.", glance));
+        return Some(RunState::AwaitingInput)
+    }
+
+    let node = match node(world, npc, &node_id) {
+        Some(node) => node,
+        None => {
+            end(world, player);
+            return None
+        }
+    };
+    let choices = offered(world, player, &node);
+    let choice = match input.parse::<usize>().ok().and_then(|n| n.checked_sub(1)).and_then(|i| choices.get(i)) {
+        Some(choice) => *choice,
+        None => {
+            let error = "Answer with the number of one of the choices, or \"bye\" to stop talking.";
+            world.write_resource::<Output>().send(player, Message::error(error));
+            return Some(RunState::AwaitingInput)
+        }
+    };
+
+    world.write_resource::<Output>().send(player, format!("You say, \"{}\"", choice.text));
+    for effect in &choice.effects {
+        apply(world, player, effect);
+    }
+    match &choice.goto {
+        Some(next) => {
+            if let Some(conversation) = world.write_storage::<components::Conversation>().get_mut(player) {
+                conversation.node = next.clone();
+            }
+            show(world, player, npc, next);
+        }
+        None => end(world, player),
+    }
+
+    // Items are handed over when the world is next maintained, which takes a turn
+    let gives = choice.effects.iter().any(|effect| matches!(effect, Effect::GiveItem(_)));
+    Some(if gives { RunState::PlayerTurn } else { RunState::AwaitingInput })
+}
+
+/// Have the NPC say a node's text and list the choices the player can answer with. Nodes with
+/// nothing left to answer end the conversation.
+fn show(world: &World, player: Entity, npc: Entity, node_id: &str) {
+    let node = match node(world, npc, node_id) {
+        Some(node) => node,
+        None => {
+            log::warn!("Entity {} has no conversation node {:?}", npc.id(), node_id);
+            end(world, player);
+            return
+        }
+    };
+    let choices = offered(world, player, &node);
+    let mut output = world.write_resource::<Output>();
+    output.send(player, format!("The {} says, \"{}\"", glance(world, npc), node.text));
+    if choices.is_empty() {
+        drop(output);
+        end(world, player);
+        return
+    }
+    let mut lines: Vec<String> = choices.iter()
+        .enumerate()
+        .map(|(i, choice)| format!("  {}. {}", i + 1, choice.text))
+        .collect();
+    lines.push("(Answer with a number, or \"bye\" to stop talking.)".to_string());
+    output.send(player, lines.join("\n"));
+}
+
+fn end(world: &World, player: Entity) {
+    world.write_storage::<components::Conversation>().remove(player);
+}
+
+fn node(world: &World, npc: Entity, node_id: &str) -> Option<DialogueNode> {
+    world.read_storage::<components::Dialogue>()
+        .get(npc)
+        .and_then(|dialogue| dialogue.conversation.get(node_id))
+        .cloned()
+}
+
+/// The choices of a node the player meets the conditions of, in order.
+fn offered<'a>(world: &World, player: Entity, node: &'a DialogueNode) -> Vec<&'a components::DialogueChoice> {
+    node.choices.iter()
+        .filter(|choice| choice.requires.iter().all(|condition| meets(world, player, condition)))
+        .collect()
+}
+
+fn meets(world: &World, player: Entity, condition: &Condition) -> bool {
+    let journals = world.read_storage::<components::Journal>();
+    let journal = journals.get(player);
+    match condition {
+        Condition::HasItem(x) => {
+            let storages = world.read_storage::<components::Storage>();
+            let equipped = world.read_storage::<components::Equipped>();
+            let ds = world.read_storage::<components::Description>();
+            let carried = storages.get(player).map(|storage| storage.items.iter()).into_iter().flatten();
+            let worn = equipped.get(player).map(|equipped| equipped.slots.values()).into_iter().flatten();
+            carried.chain(worn)
+                .filter_map(|item| ds.get(*item))
+                .any(|d| d.glance.eq_ignore_ascii_case(x) || d.name.as_ref().is_some_and(|name| name.eq_ignore_ascii_case(x)))
+        }
+        Condition::Visited(room) => {
+            let map = world.read_resource::<Map>();
+            map.room_named(room)
+                .is_some_and(|room| journal.is_some_and(|journal| journal.visited.contains(&room)))
+        }
+        Condition::Flag(flag) => journal.is_some_and(|journal| journal.flags.contains(flag)),
+        Condition::QuestStage(quest, stage) => journal.is_some_and(|journal| journal.quest_stage(quest) >= *stage),
+        Condition::Not(condition) => !meets(world, player, condition),
+    }
+}
+
+fn apply(world: &World, player: Entity, effect: &Effect) {
+    let mut journals = world.write_storage::<components::Journal>();
+    match effect {
+        Effect::GiveItem(prefab) => {
+            let prefab = prefab.clone();
+            world.read_resource::<LazyUpdate>().exec_mut(move |ecs| {
+                let item = match content::spawn_detached(&prefab, ecs) {
+                    Ok(item) => item,
+                    Err(e) => {
+                        log::warn!("Failed to give player {} a {:?}: {}", player.id(), prefab, e);
+                        return
+                    }
+                };
+                if let Some(storage) = ecs.write_storage::<components::Storage>().get_mut(player) {
+                    storage.items.push(item);
+                }
+                let glance = glance(ecs, item);
+                ecs.write_resource::<Output>().send(player, format!("You receive a {}.", glance));
+            });
+        }
+        Effect::Unlock(room, dir) => {
+            let mut map = world.write_resource::<Map>();
+            match map.room_named(room) {
+                Some(room) => map.update_gate(&room, dir, |gate| gate.set_locked(false)),
+                None => log::warn!("Can't unlock an exit out of unknown room {:?}", room),
+            }
+        }
+        Effect::SetFlag(flag) => {
+            if let Some(journal) = journals.get_mut(player) {
+                journal.flags.insert(flag.clone());
+            }
+        }
+        Effect::ClearFlag(flag) => {
+            if let Some(journal) = journals.get_mut(player) {
+                journal.flags.remove(flag);
+            }
+        }
+        Effect::SetQuestStage(quest, stage) => {
+            if let Some(journal) = journals.get_mut(player) {
+                journal.quests.insert(quest.clone(), *stage);
+            }
+        }
+    }
+}
+
+/// Check whether an NPC is still in the player's room to talk to.
+fn is_around(world: &World, player: Entity, npc: Entity) -> bool {
+    let inrooms = world.read_storage::<components::InRoom>();
+    world.entities().is_alive(npc) && match (inrooms.get(player), inrooms.get(npc)) {
+        (Some(a), Some(b)) => a.room == b.room,
+        _ => false,
+    }
+}
+
+fn glance(world: &World, entity: Entity) -> String {
+    world.read_storage::<components::Description>()
+        .get(entity)
+        .map_or("someone".to_string(), |d| d.glance.clone())
+}
@@ -0,0 +1,188 @@
+// Loot tables.
+//
+// A loot table lists what might turn up in a container or on a corpse, as weighted entries which
+// each drop some number of a prefab, roll on another table, or drop nothing at all. Tables are
+// kept in a loot file along with which table foes of each difficulty drop:
+//
+//     #![enable(implicit_some)]
+//     (
+//         tables: {
+//             "scrap": (
+//                 rolls: (0, 2),
+//                 entries: [
+//                     (prefab: "bolt", count: (1, 3)),
+//                     (prefab: "spoon", rarity: Uncommon),
+//                     (weight: 50),
+//                 ],
+//             ),
+//             "pirate_booty": (
+//                 entries: [
+//                     (table: "scrap"),
+//                     (prefab: "doubloon", count: (1, 5), rarity: Rare),
+//                 ],
+//             ),
+//         },
+//         difficulty: [
+//             (min: 0, table: "scrap"),
+//             (min: 2, table: "pirate_booty"),
+//         ],
+//     )
+//
+// Every table picks an entry as many times as its `rolls` range says (once, by default). An
+// entry's chance of being picked is its `weight`, or otherwise the weight of its `rarity`. Foes
+// drop the table for the highest difficulty `min` they reach.
+// ----------------------------
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use specs::prelude::*;
+
+use crate::game::components;
+use crate::game::content::{self, ContentError};
+
+
+/// The loot tables every game can roll on, unless another loot file is given.
+pub const DEFAULT_LOOT: &str = include_str!("../../loot/default.ron");
+
+/// How often an entry turns up, next to the others in its table.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    Legendary,
+}
+
+impl Rarity {
+    fn weight(self) -> u32 {
+        match self {
+            Rarity::Common => 60,
+            Rarity::Uncommon => 25,
+            Rarity::Rare => 10,
+            Rarity::Legendary => 5,
+        }
+    }
+}
+
+/// One of the things a table can drop. An entry with neither a prefab nor a table drops nothing.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct LootEntry {
+    pub prefab: Option<String>,
+    // Another table to roll on.
+    pub table: Option<String>,
+    // How many of the prefab to drop, at least and at most.
+    pub count: Option<(u32, u32)>,
+    pub weight: Option<u32>,
+    pub rarity: Option<Rarity>,
+}
+
+impl LootEntry {
+    fn weight(&self) -> u32 {
+        self.weight.unwrap_or_else(|| self.rarity.unwrap_or(Rarity::Common).weight())
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct LootTable {
+    // How many times to pick an entry, at least and at most.
+    #[serde(default = "one_roll")]
+    pub rolls: (u32, u32),
+    pub entries: Vec<LootEntry>,
+}
+
+fn one_roll() -> (u32, u32) {
+    (1, 1)
+}
+
+/// The table dropped by foes of at least some difficulty.
+#[derive(Deserialize, Clone, Debug)]
+pub struct LootTier {
+    pub min: u32,
+    pub table: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct LootTables {
+    #[serde(default)]
+    tables: HashMap<String, LootTable>,
+    #[serde(default)]
+    difficulty: Vec<LootTier>,
+}
+
+impl LootTables {
+    pub fn parse(text: &str) -> Result<Self, ContentError> {
+        Ok(ron::from_str(text)?)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, ContentError> {
+        LootTables::parse(&fs::read_to_string(path)?)
+    }
+
+    /// The table foes of a difficulty drop, if any.
+    pub fn for_difficulty(&self, difficulty: u32) -> Option<&str> {
+        self.difficulty.iter()
+            .filter(|tier| tier.min <= difficulty)
+            .max_by_key(|tier| tier.min)
+            .map(|tier| tier.table.as_str())
+    }
+
+    /// Roll on a table, returning the names of the prefabs it dropped.
+    pub fn roll(&self, table: &str) -> Result<Vec<String>, ContentError> {
+        let mut drops = Vec::new();
+        self.roll_into(table, &mut Vec::new(), &mut drops)?;
+        Ok(drops)
+    }
+
+    fn roll_into<'a>(&'a self, name: &'a str, rolling: &mut Vec<&'a str>, drops: &mut Vec<String>) -> Result<(), ContentError> {
+        if rolling.contains(&name) {
+            return Err(ContentError::RecursiveLootTable(name.to_string()))
+        }
+        let table = self.tables.get(name).ok_or_else(|| ContentError::UnknownLootTable(name.to_string()))?;
+        rolling.push(name);
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..pick_between(table.rolls, &mut rng) {
+            let entry = match table.entries.choose_weighted(&mut rng, LootEntry::weight) {
+                Ok(entry) => entry,
+                Err(_) => break,
+            };
+            if let Some(prefab) = &entry.prefab {
+                let count = pick_between(entry.count.unwrap_or((1, 1)), &mut rng);
+                drops.extend((0..count).map(|_| prefab.clone()));
+            }
+            if let Some(nested) = &entry.table {
+                self.roll_into(nested, rolling, drops)?;
+            }
+        }
+
+        rolling.pop();
+        Ok(())
+    }
+}
+
+fn pick_between((min, max): (u32, u32), rng: &mut impl Rng) -> u32 {
+    rng.gen_range(min.min(max)..=max.max(min))
+}
+
+/// Roll on a table, spawning whatever it drops into a container.
+pub fn fill(table: &str, container: Entity, ecs: &mut World) -> Result<Vec<Entity>, ContentError> {
+    let prefabs = ecs.fetch::<LootTables>().roll(table)?;
+    let items = prefabs.iter()
+        .map(|prefab| content::spawn_detached(prefab, ecs))
+        .collect::<Result<Vec<Entity>, ContentError>>()?;
+
+    let mut storages = ecs.write_storage::<components::Storage>();
+    match storages.get_mut(container) {
+        Some(store) => store.items.extend(items.iter().copied()),
+        None => {
+            storages.insert(container, components::Storage { items: items.clone() })
+                .expect("Failed to insert Storage component");
+        }
+    }
+    Ok(items)
+}
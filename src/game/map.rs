@@ -1,13 +1,64 @@
 use specs::prelude::*;
 use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use specs::error::NoError;
+use specs::saveload::{ConvertSaveload, Marker};
 use specs::world::EntitiesRes;
 use crate::game::components;
+use crate::game::output::Message;
 use crate::utils;
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ExitDirection {
     Next,
     Previous,
+    North,
+    South,
+    East,
+    West,
+    Up,
+    Down,
+    In,
+    Out,
+    // Anything else, like a "hatch" or a "ladder", named by what players call it.
+    Named(String),
+}
+
+impl ExitDirection {
+    /// Parse a direction the way players type it, taking anything which isn't one of the usual
+    /// directions as the name of an exit.
+    pub fn parse(text: &str) -> Self {
+        match text.trim().to_lowercase().as_str() {
+            "next" => ExitDirection::Next,
+            "previous" | "prev" => ExitDirection::Previous,
+            "north" | "n" => ExitDirection::North,
+            "south" | "s" => ExitDirection::South,
+            "east" | "e" => ExitDirection::East,
+            "west" | "w" => ExitDirection::West,
+            "up" | "u" => ExitDirection::Up,
+            "down" | "d" => ExitDirection::Down,
+            "in" | "inside" | "enter" => ExitDirection::In,
+            "out" | "outside" | "leave" => ExitDirection::Out,
+            name => ExitDirection::Named(name.to_string()),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            ExitDirection::Next => "next",
+            ExitDirection::Previous => "previous",
+            ExitDirection::North => "north",
+            ExitDirection::South => "south",
+            ExitDirection::East => "east",
+            ExitDirection::West => "west",
+            ExitDirection::Up => "up",
+            ExitDirection::Down => "down",
+            ExitDirection::In => "in",
+            ExitDirection::Out => "out",
+            ExitDirection::Named(name) => name,
+        }
+    }
 }
 
 pub type RoomId = u64;
@@ -15,6 +66,12 @@ pub type RoomId = u64;
 pub struct Gate {
     pub description: String,
     is_locked: bool,
+    is_closed: bool,
+    // Whether the gate shuts, and locks if it can, behind whoever goes through it.
+    relocks: bool,
+    // The exit leading back from the other room, when it is the same gate and opens, closes,
+    // locks and unlocks along with this one.
+    mirror: Option<ExitDirection>,
     keys: Option<Vec<Entity>>,
     to: RoomId,
 }
@@ -25,38 +82,80 @@ impl Gate {
             description: description.to_string(),
             to,
             is_locked: false,
+            is_closed: false,
+            relocks: false,
+            mirror: None,
             keys: None,
         }
     }
 
+    /// A closed gate which can only be unlocked with one of the keys.
     pub fn new_locked(to: RoomId, description: &str, keys: Vec<Entity>) -> Self {
         Gate {
-            description: description.to_string(),
-            to,
             keys: Some(keys),
             is_locked: true,
+            is_closed: true,
+            ..Gate::new(to, description)
         }
     }
 
-    pub fn add_key(&mut self, key: Entity) {
-        let keys = self.keys.as_mut().unwrap();
-        keys.push(key);
-        self.keys = Some(keys.to_vec());
+    /// An unlocked gate which can be locked with one of the keys.
+    pub fn new_lockable(to: RoomId, description: &str, keys: Vec<Entity>) -> Self {
+        Gate { keys: Some(keys), ..Gate::new(to, description) }
     }
 
-    pub fn try_unlock(&mut self, key: Entity) {
-        let keys = self.keys.as_ref().unwrap();
-        if keys.contains(&key) {
-            self.is_locked = false;
-        }
+    pub fn closed(mut self) -> Self {
+        self.is_closed = true;
+        self
+    }
+
+    pub fn relocking(mut self) -> Self {
+        self.relocks = true;
+        self
+    }
+
+    /// Make the gate the same one as the exit leading back from the other room, in `mirror`.
+    pub fn two_sided(mut self, mirror: ExitDirection) -> Self {
+        self.mirror = Some(mirror);
+        self
     }
 
     pub fn is_locked(&self) -> bool {
-        self.is_locked.clone()
+        self.is_locked
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.is_closed
+    }
+
+    pub fn relocks(&self) -> bool {
+        self.relocks
+    }
+
+    /// Whether the gate has a lock at all.
+    pub fn has_lock(&self) -> bool {
+        self.keys.is_some()
+    }
+
+    /// Whether an entity is one of the keys to the gate.
+    pub fn fits(&self, key: Entity) -> bool {
+        self.keys.as_ref().is_some_and(|keys| keys.contains(&key))
+    }
+
+    /// Lock or unlock the gate. Locking closes it too.
+    pub fn set_locked(&mut self, locked: bool) {
+        self.is_locked = locked;
+        if locked {
+            self.is_closed = true;
+        }
+    }
+
+    pub fn set_closed(&mut self, closed: bool) {
+        self.is_closed = closed;
     }
 
     pub fn to(&self) -> RoomId {
-        self.to.clone()
+        self.to
     }
 }
 
@@ -66,7 +165,7 @@ pub struct Room {
     pub exits: HashMap<ExitDirection, Gate>,
 }
 
-impl<'a> Room {
+impl Room {
     pub fn new(id: RoomId, description: &str) -> Self {
         Room {
             id,
@@ -75,7 +174,7 @@ impl<'a> Room {
         }
     }
 
-    pub fn id(&self) -> RoomId { self.id.clone() }
+    pub fn id(&self) -> RoomId { self.id }
 
     pub fn add_exit(&mut self, dir: ExitDirection, gate: Gate) -> &mut Self {
         self.exits.insert(dir, gate);
@@ -86,59 +185,100 @@ impl<'a> Room {
         self.exits.get(&dir)
     }
 
-    pub fn as_gate(&self, description: &str) -> Gate {
-        Gate::new(self.id.clone(), description)
+    /// Find the exit a player means, by its direction ("north", "n") or by its name along with
+    /// whatever verb they put in front of it ("hatch", "climb ladder").
+    pub fn find_exit(&self, phrase: &str) -> Option<ExitDirection> {
+        let phrase = phrase.trim().to_lowercase();
+        let dir = ExitDirection::parse(&phrase);
+        if self.exits.contains_key(&dir) {
+            return Some(dir)
+        }
+        self.exits.keys()
+            .find(|dir| match dir {
+                ExitDirection::Named(name) => {
+                    let name = name.to_lowercase();
+                    phrase == name || phrase.ends_with(&format!(" {}", name))
+                }
+                _ => false,
+            })
+            .cloned()
     }
 
-    pub fn as_gate_locked(&self, description: &str, keys: Vec<Entity>) -> Gate {
-        Gate::new_locked(self.id.clone(), description, keys)
+    /// Describe the room to a viewer, along with everything and everyone else in it which can be seen.
+    pub fn description(
+        &self,
+        viewer: Entity,
+        entities: &EntitiesRes,
+        inrooms: &mut WriteStorage<components::InRoom>,
+        ds: &ReadStorage<components::Description>,
+        names: &ReadStorage<components::DisplayName>,
+    ) -> Message {
+        let mut obj_glances: Vec<String> = Vec::new();
+        let mut players: Vec<String> = Vec::new();
+        for (e, inroom) in (entities, inrooms).join() {
+            if inroom.room == self.id {
+                if let Some(d) = ds.get(e) {
+                    obj_glances.push(d.glance.clone());
+                } else if let Some(name) = names.get(e).filter(|_| e != viewer) {
+                    players.push(name.name.clone());
+                }
+            }
+        }
+        let description = self.description.split("===").next().unwrap().trim().to_string();
+        Message::Room { description, contents: obj_glances, players }
     }
+}
 
-    fn generate_room_description(&self, glances: &Vec<&str>) -> String {
-        let mut final_str;
-        let desc = self.description.clone();
-        let mut split = desc.split("===");
-        final_str = split.next().unwrap().trim().to_string();
-        let mut obj_strings = glances.iter()
-            .map(|glance| { utils::aan(*glance) })
-            .collect::<Vec<String>>();
-        obj_strings.retain(|x| x.len() > 0);
+/// Render a room description followed by a sentence listing what can be seen in the room, and
+/// another listing who else is there.
+pub fn format_room_description(description: &str, glances: &[String], players: &[String]) -> String {
+    let mut final_str;
+    let mut split = description.split("===");
+    final_str = split.next().unwrap().trim().to_string();
+    let mut obj_strings = glances.iter()
+        .map(|glance| { utils::aan(glance) })
+        .collect::<Vec<String>>();
+    obj_strings.retain(|x| !x.is_empty());
 
-        if obj_strings.len() > 0 {
-            final_str = format!("{}\n===\nYou see ", &final_str);
-        }
+    if !obj_strings.is_empty() {
+        final_str = format!("{}\n===\nYou see ", &final_str);
+    }
 
-        for i in 0..obj_strings.len() {
-            if i == (obj_strings.len() - 1) {
-                if i == 1 {
-                    final_str = format!("{} and ", &final_str);
-                } else if i > 1 {
-                    final_str = format!("{}, and ", &final_str);
-                }
-            } else if i > 0 {
-                final_str = format!("{}, ", &final_str);
+    for i in 0..obj_strings.len() {
+        if i == (obj_strings.len() - 1) {
+            if i == 1 {
+                final_str = format!("{} and ", &final_str);
+            } else if i > 1 {
+                final_str = format!("{}, and ", &final_str);
             }
+        } else if i > 0 {
+            final_str = format!("{}, ", &final_str);
+        }
 
-            final_str = format!("{}{}", &final_str, &obj_strings[i]);
+        final_str = format!("{}{}", &final_str, &obj_strings[i]);
 
-            if i == (obj_strings.len() - 1) {
-                final_str = format!("{}.", &final_str);
-            }
+        if i == (obj_strings.len() - 1) {
+            final_str = format!("{}.", &final_str);
         }
-        final_str
     }
 
-    pub fn description(&self, entities: &EntitiesRes, inrooms: &mut WriteStorage<components::InRoom>, ds: &ReadStorage<components::Description>) -> String {
-        let mut obj_glances: Vec<&str> = Vec::new();
-        for (e, inroom) in (entities, inrooms).join() {
-            if inroom.room == self.id {
-                match ds.get(e) {
-                    Some(d) => obj_glances.push(&d.glance),
-                    None => {},
-                }
-            }
+    if !players.is_empty() {
+        if obj_strings.is_empty() {
+            final_str = format!("{}\n===", &final_str);
         }
-        self.generate_room_description(&obj_glances)
+        let verb = if players.len() == 1 { "is" } else { "are" };
+        final_str = format!("{}\n{} {} here.", &final_str, list_names(players), verb);
+    }
+    final_str
+}
+
+/// List names in a sentence: "Alice", "Alice and Bob", "Alice, Bob, and Carol".
+fn list_names(names: &[String]) -> String {
+    match names {
+        [] => String::new(),
+        [only] => only.clone(),
+        [first, second] => format!("{} and {}", first, second),
+        [rest @ .., last] => format!("{}, and {}", rest.join(", "), last),
     }
 }
 
@@ -147,6 +287,8 @@ pub struct Map {
     pub rooms: BTreeMap<RoomId, Room>,
     room_indexer: u64,
     spawn: Option<RoomId>,
+    // The ids content files know rooms by.
+    names: HashMap<String, RoomId>,
 }
 
 impl Map {
@@ -155,13 +297,14 @@ impl Map {
             rooms: BTreeMap::new(),
             room_indexer: 0,
             spawn: None,
+            names: HashMap::new(),
         }
     }
 
     pub fn create_room(&mut self, description: &str) -> Room {
-        let room_id = self.room_indexer.clone();
+        let room_id = self.room_indexer;
         self.room_indexer += 1;
-        Room::new(room_id.clone(), description)
+        Room::new(room_id, description)
     }
 
     pub fn set_spawn(&mut self, room_id: RoomId) {
@@ -172,6 +315,16 @@ impl Map {
         self.spawn.expect("No spawn point set for map!")
     }
 
+    /// Remember the id a content file gave a room.
+    pub fn name_room(&mut self, name: &str, room_id: RoomId) {
+        self.names.insert(name.to_string(), room_id);
+    }
+
+    /// Find a room by the id its content file gave it.
+    pub fn room_named(&self, name: &str) -> Option<RoomId> {
+        self.names.get(name).copied()
+    }
+
     pub fn room_mut(&mut self, room_id: &RoomId) -> &mut Room {
         self.rooms.get_mut(room_id).unwrap()
     }
@@ -179,4 +332,122 @@ impl Map {
     pub fn room(&self, room_id: &RoomId) -> &Room {
         self.rooms.get(room_id).unwrap()
     }
-}
\ No newline at end of file
+
+    /// Change the gate of an exit, along with the exit on the other side when the gate is two-sided.
+    pub fn update_gate<F>(&mut self, room_id: &RoomId, dir: &ExitDirection, update: F)
+    where
+        F: Fn(&mut Gate),
+    {
+        let gate = match self.room_mut(room_id).exits.get_mut(dir) {
+            Some(gate) => gate,
+            None => return,
+        };
+        update(gate);
+        let (to, mirror) = match &gate.mirror {
+            Some(mirror) => (gate.to, mirror.clone()),
+            None => return,
+        };
+        if let Some(mirror) = self.room_mut(&to).exits.get_mut(&mirror) {
+            update(mirror);
+        }
+    }
+}
+
+
+/// A saved `Gate`, holding the markers of its keys instead of the entities.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GateData<M> {
+    description: String,
+    is_locked: bool,
+    #[serde(default)]
+    is_closed: bool,
+    #[serde(default)]
+    relocks: bool,
+    #[serde(default)]
+    mirror: Option<ExitDirection>,
+    keys: Option<Vec<M>>,
+    to: RoomId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RoomData<M> {
+    id: RoomId,
+    description: String,
+    exits: Vec<(ExitDirection, GateData<M>)>,
+}
+
+/// A saved `Map`, along with the lock state of every gate.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MapData<M> {
+    rooms: Vec<RoomData<M>>,
+    room_indexer: u64,
+    spawn: Option<RoomId>,
+    #[serde(default)]
+    names: HashMap<String, RoomId>,
+}
+
+impl<M> ConvertSaveload<M> for Map
+where
+    M: Marker + Serialize + DeserializeOwned,
+{
+    type Data = MapData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        let rooms = self.rooms.values()
+            .map(|room| RoomData {
+                id: room.id,
+                description: room.description.clone(),
+                exits: room.exits.iter()
+                    .map(|(dir, gate)| (dir.clone(), GateData {
+                        description: gate.description.clone(),
+                        is_locked: gate.is_locked,
+                        is_closed: gate.is_closed,
+                        relocks: gate.relocks,
+                        mirror: gate.mirror.clone(),
+                        keys: gate.keys.as_ref()
+                            .map(|keys| keys.iter().filter_map(|key| ids(*key)).collect()),
+                        to: gate.to,
+                    }))
+                    .collect(),
+            })
+            .collect();
+        Ok(MapData {
+            rooms,
+            room_indexer: self.room_indexer,
+            spawn: self.spawn,
+            names: self.names.clone(),
+        })
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        let mut map = Map {
+            rooms: BTreeMap::new(),
+            room_indexer: data.room_indexer,
+            spawn: data.spawn,
+            names: data.names,
+        };
+        for room in data.rooms {
+            let exits = room.exits.into_iter()
+                .map(|(dir, gate)| (dir, Gate {
+                    description: gate.description,
+                    is_locked: gate.is_locked,
+                    is_closed: gate.is_closed,
+                    relocks: gate.relocks,
+                    mirror: gate.mirror,
+                    keys: gate.keys
+                        .map(|keys| keys.into_iter().filter_map(&mut ids).collect()),
+                    to: gate.to,
+                }))
+                .collect();
+            map.rooms.insert(room.id, Room { id: room.id, description: room.description, exits });
+        }
+        Ok(map)
+    }
+}
@@ -1,13 +1,31 @@
-mod components;
+pub(crate) mod accounts;
+pub(crate) mod commands;
+pub(crate) mod components;
+mod content;
+pub(crate) mod conversation;
+mod loot;
 mod systems;
-mod map;
+pub(crate) mod map;
 mod player;
-mod manual_map;
+mod output;
+mod saveload;
+pub(crate) mod targeting;
+mod transfer;
 
-use std::collections::BTreeMap;
-use specs::{World, WorldExt, Builder};
+use std::env;
+use std::path::Path;
+use specs::{World, WorldExt, Join};
 use specs::world::Index as EntityId;
-use specs;
+
+use crate::cogs;
+
+pub use accounts::LoginError;
+pub use content::{ContentError, MapFile, Prefabs};
+pub use loot::LootTables;
+pub use map::RoomId;
+pub use output::{ConsoleSink, Message, Output, OutputSink};
+pub use saveload::SaveError;
+pub use transfer::{ItemState, PlayerState};
 
 
 #[derive(PartialEq, Copy, Clone)]
@@ -21,92 +39,91 @@ pub enum RunState {
 pub struct Game<'a, 'b> {
     world: World,
     dispatcher: specs::Dispatcher<'a, 'b>,
-    player_id: EntityId,
+    commands: commands::Commands,
 }
 
 impl Game<'_, '_> {
-    /// Create a new game object.
+    /// Create a new game on the map file named by the `MUD_MAP` environment variable, with the
+    /// prefabs in the file named by `MUD_PREFABS` and the loot tables in the file named by
+    /// `MUD_LOOT`. The defaults are used for whichever isn't set.
     pub fn new<'a, 'b>() -> Game<'a, 'b> {
+        let map_file = match env::var("MUD_MAP") {
+            Ok(path) => MapFile::read(Path::new(&path))
+                .unwrap_or_else(|e| panic!("Failed to load map {}: {}", path, e)),
+            Err(_) => MapFile::parse(content::DEFAULT_MAP)
+                .unwrap_or_else(|e| panic!("Failed to load the default map: {}", e)),
+        };
+        let prefabs = match env::var("MUD_PREFABS") {
+            Ok(path) => Prefabs::read(Path::new(&path))
+                .unwrap_or_else(|e| panic!("Failed to load prefabs {}: {}", path, e)),
+            Err(_) => Prefabs::parse(content::DEFAULT_PREFABS)
+                .unwrap_or_else(|e| panic!("Failed to load the default prefabs: {}", e)),
+        };
+        let loot = match env::var("MUD_LOOT") {
+            Ok(path) => LootTables::read(Path::new(&path))
+                .unwrap_or_else(|e| panic!("Failed to load loot tables {}: {}", path, e)),
+            Err(_) => LootTables::parse(loot::DEFAULT_LOOT)
+                .unwrap_or_else(|e| panic!("Failed to load the default loot tables: {}", e)),
+        };
+        Game::with_content(&map_file, prefabs, loot).unwrap_or_else(|e| panic!("Failed to build map: {}", e))
+    }
+
+    /// Create a new game on the map described by a map file, which can spawn the given prefabs
+    /// and roll on the given loot tables.
+    pub fn with_content<'a, 'b>(map_file: &MapFile, prefabs: Prefabs, loot: LootTables) -> Result<Game<'a, 'b>, ContentError> {
         let mut world = World::new();
         let mut dispatcher = specs::DispatcherBuilder::new()
-            .with(systems::MovementSystem, "movement", &[])
-            .with(systems::InventorySystem, "inventory", &[])
+            .with(systems::NpcSystem, "npc", &[])
+            .with(systems::MovementSystem, "movement", &["npc"])
+            .with(systems::TheftSystem, "theft", &[])
+            .with(systems::UseSystem, "use", &[])
+            .with(systems::InventorySystem, "inventory", &["theft", "use"])
+            .with(systems::EquipmentSystem, "equipment", &[])
+            .with(systems::CombatSystem, "combat", &["npc"])
+            // Whatever changes hands this turn does so before the dead drop everything
+            .with(systems::DeathSystem, "death", &["combat", "inventory"])
             .build();
 
         // Any components not mentioned in systems must be manually mentioned here
         world.register::<components::Player>();
+        world.register::<components::DisplayName>();
         world.register::<components::Item>();
+        world.register::<components::Npc>();
+        world.register::<components::ActionQueue>();
+        world.register::<components::Parked>();
+        world.register::<components::Dialogue>();
+        world.register::<components::Conversation>();
+        world.register::<saveload::SaveMarker>();
+        world.insert(saveload::SaveMarkerAllocator::new());
 
         dispatcher.setup(&mut world);
 
-        let world_map = Game::build_map();
-
-        world.create_entity()
-            .with(components::Description {
-                description: "It looks like a sturdy table!".to_string(),
-                glance: "table".to_string(),
-                name: None,
-            })
-            .with(components::InRoom { room: world_map.spawn() })
-            .build();
-        world.create_entity()
-            .with(components::Description {
-                description: "It looks like a metal cabinet.".to_string(),
-                glance: "cabinet".to_string(),
-                name: None,
-            })
-            .with(components::InRoom { room: world_map.spawn() })
-            .with(components::Storage::new())
-            .build();
-        world.create_entity()
-            .with(components::Description {
-                description: "A double reed woodwind instrument.".to_string(),
-                glance: "oboe".to_string(),
-                name: None,
-            })
-            .with(components::InRoom { room: world_map.spawn() })
-            .with(components::Item{})
-            .build();
-
-        let player = world.create_entity()
-            .with(components::Player{})
-            .with(components::InRoom { room: world_map.spawn() })
-            .with(components::Storage::new())
-            .build();
-
+        world.insert(prefabs);
+        world.insert(loot);
+        let world_map = content::build(map_file, &mut world)?;
         world.insert(world_map);
         world.insert(RunState::PreRun);
+        world.insert(Output::new());
+        world.insert(accounts::Accounts::new());
 
-        Game {
+        let mut commands = commands::Commands::new();
+        for cog in cogs::default_cogs() {
+            commands.register_cog(cog.as_ref());
+        }
+
+        Ok(Game {
             world,
             dispatcher,
-            player_id: player.id(),
-        }
+            commands,
+        })
     }
 
     /// Get the world spawn point.
     fn spawn(&self) -> map::RoomId {
-        let world_map: &map::Map = &*self.world.fetch::<map::Map>();
+        let world_map: &map::Map = &self.world.fetch::<map::Map>();
         world_map.spawn()
     }
 
-    /// Build the default map.
-    pub fn build_map() -> map::Map {
-        let mut map = map::Map::new();
-        let mut first_room = map.create_room("This is the first room");
-        let mut second_room = map.create_room("This is the second room");
-        second_room
-            .add_exit(map::ExitDirection::Previous, first_room.as_gate("Hallway leading to the previous train car."));
-        first_room
-            .add_exit(map::ExitDirection::Next, second_room.as_gate("Hallway leading to the next train car."));
-
-        map.set_spawn(first_room.id());
-        map.rooms.insert(first_room.id(), first_room);
-        map.rooms.insert(second_room.id(), second_room);
-
-        map
-    }
-
     /// Game tick
     pub fn tick(&mut self) {
         let mut newrunstate: RunState;
@@ -117,23 +134,23 @@ impl Game<'_, '_> {
 
         match newrunstate {
             RunState::PreRun => {
-                println!("=============== prerun tick ===============");
+                log::debug!("=============== prerun tick ===============");
                 self.dispatcher.dispatch(&self.world);
                 self.world.maintain();
                 newrunstate = RunState::AwaitingInput;
             }
             RunState::AwaitingInput => {
-                println!("=============== input  tick ===============");
+                log::debug!("=============== input  tick ===============");
                 newrunstate = player::handle_player_input(self);
             }
             RunState::PlayerTurn => {
-                println!("=============== player tick ===============");
+                log::debug!("=============== player tick ===============");
                 self.dispatcher.dispatch(&self.world);
                 self.world.maintain();
                 newrunstate = RunState::NpcTurn;
             }
             RunState::NpcTurn => {
-                println!("=============== NPC    tick ===============");
+                log::debug!("=============== NPC    tick ===============");
                 self.dispatcher.dispatch(&self.world);
                 self.world.maintain();
                 newrunstate = RunState::AwaitingInput;
@@ -146,37 +163,125 @@ impl Game<'_, '_> {
         }
     }
 
-    /// Create a player entity, inject into the world, and return the player entity id.
-    pub fn create_player(&mut self) -> EntityId {
-        let spawn: map::RoomId;
-        {
-            spawn = self.spawn()
+    /// Log in to an account, returning the id of its character.
+    ///
+    /// The first login to an account creates a new character at the world spawn point, while
+    /// later ones pick the character back up wherever it was left.
+    pub fn login(&mut self, account: &str) -> Result<EntityId, LoginError> {
+        let spawn = self.spawn();
+        accounts::login(account, spawn, &mut self.world).map(|player| player.id())
+    }
+
+    /// Log out of an account. Its character is parked until the next login.
+    pub fn logout(&mut self, account: &str) {
+        accounts::logout(account, &mut self.world);
+    }
+
+    /// Get the id of the character belonging to an account.
+    pub fn account_player(&self, account: &str) -> Option<EntityId> {
+        self.world.fetch::<accounts::Accounts>()
+            .character(account)
+            .map(|player| player.id())
+    }
+
+    /// Set the name other players see a player by.
+    pub fn set_player_name(&mut self, player_id: EntityId, name: &str) {
+        let player = self.world.entities().entity(player_id);
+        self.world.write_storage::<components::DisplayName>()
+            .insert(player, components::DisplayName { name: name.to_string() })
+            .expect("Failed to insert DisplayName component");
+    }
+
+    /// Pack up a player and remove them, and everything they carry, from the world. Their
+    /// account is forgotten along with them.
+    ///
+    /// Returns `None` if there is no such player.
+    pub fn remove_player(&mut self, player_id: EntityId) -> Option<PlayerState> {
+        let player = self.world.entities().entity(player_id);
+        if !self.world.read_storage::<components::Player>().contains(player) {
+            return None
         }
-        println!("Creating player entity");
-        let player = self.world.create_entity()
-            .with(components::Player{})
-            .with(components::InRoom { room: spawn })
-            .build();
-        println!("About to return player id");
-        player.id()
+        Some(transfer::remove_player(player, &mut self.world))
+    }
+
+    /// Recreate a player packed up by `remove_player` at the world spawn point, logged in to the
+    /// given account, and return the new player entity id.
+    pub fn add_player(&mut self, account: &str, state: &PlayerState) -> Result<EntityId, LoginError> {
+        if let Some(player_id) = self.account_player(account) {
+            if self.world.fetch::<accounts::Accounts>().is_online(account) {
+                return Err(LoginError::AlreadyConnected)
+            }
+            self.remove_player(player_id);
+        }
+
+        let spawn = self.spawn();
+        let player = transfer::add_player(state, spawn, &mut self.world);
+        self.world.write_resource::<accounts::Accounts>().register(account, player)?;
+        Ok(player.id())
+    }
+
+    /// Get every account which has a character in the world.
+    pub fn accounts(&self) -> Vec<String> {
+        self.world.fetch::<accounts::Accounts>().accounts()
     }
 
-    /// Collect player input and inject into the ECS
+    /// Save the whole world to a file.
+    pub fn save(&mut self, path: &Path) -> Result<(), SaveError> {
+        saveload::save(path, &mut self.world)
+    }
+
+    /// Replace the world with one saved by `save`. Every account is logged out, so the ids of
+    /// their characters are only known again once they log back in.
+    pub fn load(&mut self, path: &Path) -> Result<(), SaveError> {
+        saveload::load(path, &mut self.world)
+    }
+
+    /// Find a room by the id it was given in the map file.
+    pub fn room_named(&self, name: &str) -> Option<RoomId> {
+        self.world.fetch::<map::Map>().room_named(name)
+    }
+
+    /// Get the room a player is currently in.
+    pub fn player_room(&self, player_id: EntityId) -> Option<RoomId> {
+        let player = self.world.entities().entity(player_id);
+        self.world.read_storage::<components::InRoom>()
+            .get(player)
+            .map(|inroom| inroom.room)
+    }
+
+    /// Queue input from a player, to be handled on one of their upcoming turns.
     pub fn player_input(&mut self, player_id: EntityId, input: &str) {
-        let p: player::PlayerInput = player::PlayerInput {
-            player_id,
-            input: input.into(),
-        };
+        let player = self.world.entities().entity(player_id);
+        if self.world.read_storage::<components::Parked>().contains(player) {
+            log::warn!("Dropping input for logged out player {}: {:?}", player_id, input);
+            return
+        }
+        match self.world.write_storage::<components::ActionQueue>().get_mut(player) {
+            Some(queue) => queue.actions.push_back(input.into()),
+            None => log::warn!("Dropping input for unknown player {}: {:?}", player_id, input),
+        }
+    }
 
-        let mut list: Vec<player::PlayerInput>;
-        {
-            match self.world.try_fetch::<Vec<player::PlayerInput>>() {
-                Some(l) => list = (*l).clone(),
-                None => list = Vec::new(),
+    /// Check whether any player still has commands waiting for their turn.
+    fn has_pending_input(&self) -> bool {
+        self.world.read_storage::<components::ActionQueue>()
+            .join()
+            .any(|queue| !queue.actions.is_empty())
+    }
+
+    /// Tick the game until all queued input has been handled and it is waiting on players again.
+    pub fn run_until_idle(&mut self) {
+        loop {
+            self.tick();
+            let awaiting_input = *self.world.fetch::<RunState>() == RunState::AwaitingInput;
+            if awaiting_input && !self.has_pending_input() {
+                break
             }
         }
+    }
 
-        list.push(p);
-        self.world.insert(list);
+    /// Deliver all of the text the game has produced since the last call.
+    pub fn flush_output(&mut self, sink: &mut dyn OutputSink) {
+        self.world.write_resource::<Output>().flush(sink);
     }
 }
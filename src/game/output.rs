@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use serde::Serialize;
+use specs::Entity;
+use specs::world::Index as EntityId;
+use crate::game::map;
+
+
+/// Something the game has to tell a player.
+///
+/// Text front-ends render messages with `Display`, while structured front-ends can serialize
+/// them as they are.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    // Narration and feedback on what the player did.
+    Text { text: String },
+
+    // A command which could not be carried out.
+    Error { text: String },
+
+    // The room the player is in, the glances of everything they can see in it, and the names of
+    // the other players there.
+    Room { description: String, contents: Vec<String>, players: Vec<String> },
+
+    // Items held by the player, or by a named container when `container` is set.
+    Inventory { container: Option<String>, items: Vec<String> },
+}
+
+impl Message {
+    pub fn error<S: Into<String>>(text: S) -> Self {
+        Message::Error { text: text.into() }
+    }
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Message::Text { text }
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Message::Text { text: text.to_string() }
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Message::Text { text } | Message::Error { text } => write!(f, "{}", text),
+            Message::Room { description, contents, players } => {
+                write!(f, "{}", map::format_room_description(description, contents, players))
+            }
+            Message::Inventory { container: None, items } if items.is_empty() => {
+                write!(f, "Your inventory is empty!")
+            }
+            Message::Inventory { container: Some(container), items } if items.is_empty() => {
+                write!(f, "The {} is empty", container)
+            }
+            Message::Inventory { container, items } => {
+                match container {
+                    Some(container) => write!(f, "{} inventory -----", container)?,
+                    None => write!(f, "Your inventory -----")?,
+                }
+                for item in items {
+                    write!(f, "\n- {}", item)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Somewhere the text produced by the game can be delivered, like a terminal or a chat channel.
+pub trait OutputSink {
+    /// Deliver messages addressed to a player.
+    fn send(&mut self, player: EntityId, messages: Vec<Message>);
+}
+
+/// Prints everything to stdout, regardless of which player it was meant for.
+pub struct ConsoleSink;
+
+impl OutputSink for ConsoleSink {
+    fn send(&mut self, _player: EntityId, messages: Vec<Message>) {
+        for message in messages {
+            println!("{}", message);
+        }
+    }
+}
+
+/// Messages produced by the game which are waiting to be delivered, queued per player.
+///
+/// Systems and command handlers queue messages here instead of printing them, and the front-end
+/// flushes the queues into its `OutputSink` once the game is idle.
+#[derive(Default, Debug)]
+pub struct Output {
+    messages: BTreeMap<EntityId, Vec<Message>>,
+}
+
+impl Output {
+    pub fn new() -> Self {
+        Output { messages: BTreeMap::new() }
+    }
+
+    /// Queue a message for a player.
+    pub fn send<M: Into<Message>>(&mut self, player: Entity, message: M) {
+        self.messages
+            .entry(player.id())
+            .or_default()
+            .push(message.into());
+    }
+
+    /// Deliver every queued message to the sink, leaving the queues empty.
+    pub fn flush(&mut self, sink: &mut dyn OutputSink) {
+        for (player, messages) in std::mem::take(&mut self.messages) {
+            sink.send(player, messages);
+        }
+    }
+}
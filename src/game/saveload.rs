@@ -0,0 +1,223 @@
+// Saving worlds to disk and loading them back.
+//
+// Every entity is given a marker when the world is saved, so anything referring to other entities
+// (the items in a storage, the keys to a gate, the character of an account) can be written out as
+// markers and pointed back at the right entities on load. The world is saved as a JSON document
+// holding each component storage under its component's name, next to the map and the accounts.
+// ----------------------------
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::prelude::*;
+use specs::saveload::{ConvertSaveload, Marker, MarkerAllocator, SimpleMarker, SimpleMarkerAllocator};
+
+use crate::game::accounts::{self, Accounts, AccountsData};
+use crate::game::components::{
+    ActionQueue, Awareness, Behaviour, CombatStats, Corpse, Description, Dialogue, DisplayName,
+    DropsLoot, Equippable, Equipped, Health, InRoom, Item, Journal, Npc, Parked, Player, Stealth,
+    Storage, StorageAccess, Usable, Wary,
+};
+use crate::game::map::{Map, MapData};
+use crate::game::output::Output;
+
+
+/// Marks entities which are written to save files.
+pub struct Saved;
+
+pub type SaveMarker = SimpleMarker<Saved>;
+pub type SaveMarkerAllocator = SimpleMarkerAllocator<Saved>;
+
+#[derive(Debug)]
+pub enum SaveError {
+    // The save file couldn't be read or written.
+    Io(io::Error),
+
+    // The save file isn't a saved world.
+    Format(serde_json::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "Failed to access save file: {}", e),
+            SaveError::Format(e) => write!(f, "Invalid save file: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        SaveError::Format(e)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveGame {
+    components: BTreeMap<String, serde_json::Value>,
+    map: MapData<SaveMarker>,
+    accounts: AccountsData<SaveMarker>,
+}
+
+// Save the storage of each component into the map under the component's name.
+macro_rules! serialize_individually {
+    ($ecs:expr, $saved:expr, $( $type:ident ),*) => {
+        $(
+            $saved.insert(stringify!($type).to_string(), serialize_storage::<$type>($ecs)?);
+        )*
+    };
+}
+
+// Read the storage of each component saved under the component's name, ready to be loaded.
+macro_rules! deserialize_individually {
+    ($loads:expr, $saved:expr, $( $type:ident ),*) => {
+        $(
+            if let Some(value) = $saved.remove(stringify!($type)) {
+                $loads.push(deserialize_storage::<$type>(value)?);
+            }
+        )*
+    };
+}
+
+/// A saved component storage which has been read, and only needs inserting into the world.
+type LoadStorage = Box<dyn FnOnce(&World)>;
+
+/// Write the whole world to a file.
+pub fn save(path: &Path, ecs: &mut World) -> Result<(), SaveError> {
+    mark_all(ecs);
+
+    let mut components: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+    serialize_individually!(
+        ecs, components,
+        Description, InRoom, Player, Npc, Item, Storage, Parked, Health, CombatStats, Corpse,
+        DropsLoot, StorageAccess, Behaviour, Dialogue, Journal, Stealth, Awareness, Wary, Usable,
+        Equippable, Equipped, DisplayName
+    );
+
+    let (map, accounts) = {
+        let markers = ecs.read_storage::<SaveMarker>();
+        let ids = |entity: Entity| markers.get(entity).copied();
+        (
+            ecs.fetch::<Map>().convert_into(ids).unwrap(),
+            ecs.fetch::<Accounts>().convert_into(ids).unwrap(),
+        )
+    };
+
+    let json = serde_json::to_string(&SaveGame { components, map, accounts })?;
+
+    // Write to a scratch file first, so a crash part way through can't ruin the last save
+    let scratch = path.with_extension("tmp");
+    fs::write(&scratch, json)?;
+    fs::rename(&scratch, path)?;
+    Ok(())
+}
+
+/// Replace everything in the world with what was saved to a file.
+///
+/// Every account is logged out, with its character parked where it was. The whole file is read
+/// before anything is replaced, so the world is left alone if any of it is invalid.
+pub fn load(path: &Path, ecs: &mut World) -> Result<(), SaveError> {
+    let json = fs::read_to_string(path)?;
+    let SaveGame { mut components, map, accounts } = serde_json::from_str(&json)?;
+
+    let mut loads: Vec<LoadStorage> = Vec::new();
+    deserialize_individually!(
+        loads, components,
+        Description, InRoom, Player, Npc, Item, Storage, Parked, Health, CombatStats, Corpse,
+        DropsLoot, StorageAccess, Behaviour, Dialogue, Journal, Stealth, Awareness, Wary, Usable,
+        Equippable, Equipped, DisplayName
+    );
+
+    ecs.delete_all();
+    ecs.maintain();
+    ecs.insert(SaveMarkerAllocator::new());
+    for load in loads {
+        load(ecs);
+    }
+
+    let (map, accounts) = {
+        let allocator = ecs.fetch::<SaveMarkerAllocator>();
+        let ids = |marker: SaveMarker| allocator.retrieve_entity_internal(marker.id());
+        (
+            <Map as ConvertSaveload<SaveMarker>>::convert_from(map, ids).unwrap(),
+            <Accounts as ConvertSaveload<SaveMarker>>::convert_from(accounts, ids).unwrap(),
+        )
+    };
+    ecs.insert(map);
+    ecs.insert(accounts);
+    ecs.insert(Output::new());
+
+    // Action queues only hold input from the last session, so every player starts with a new one
+    let players: Vec<Entity> = (&ecs.entities(), &ecs.read_storage::<Player>()).join()
+        .map(|(player, _)| player)
+        .collect();
+    for player in players {
+        ecs.write_storage::<ActionQueue>()
+            .insert(player, ActionQueue::new())
+            .expect("Failed to insert ActionQueue component");
+        accounts::park(player, ecs);
+    }
+    Ok(())
+}
+
+/// Save every marked entity's component of one type, as pairs of markers and saved components.
+fn serialize_storage<C>(ecs: &World) -> Result<serde_json::Value, SaveError>
+where
+    C: Component + ConvertSaveload<SaveMarker, Error = NoError>,
+{
+    let storage = ecs.read_storage::<C>();
+    let markers = ecs.read_storage::<SaveMarker>();
+    let ids = |entity: Entity| markers.get(entity).copied();
+    let saved = (&storage, &markers).join()
+        .map(|(component, marker)| match component.convert_into(ids) {
+            Ok(data) => (*marker, data),
+            Err(e) => match e {},
+        })
+        .collect::<Vec<(SaveMarker, C::Data)>>();
+    Ok(serde_json::to_value(saved)?)
+}
+
+/// Read components saved by `serialize_storage`. Loading them into the world creates any
+/// entities which don't exist yet.
+fn deserialize_storage<C>(value: serde_json::Value) -> Result<LoadStorage, SaveError>
+where
+    C: Component + ConvertSaveload<SaveMarker, Error = NoError>,
+    C::Data: 'static,
+{
+    let saved: Vec<(SaveMarker, C::Data)> = serde_json::from_value(value)?;
+    Ok(Box::new(move |ecs: &World| {
+        let entities = ecs.entities();
+        let mut storage = ecs.write_storage::<C>();
+        let mut markers = ecs.write_storage::<SaveMarker>();
+        let mut allocator = ecs.write_resource::<SaveMarkerAllocator>();
+        for (marker, data) in saved {
+            let entity = allocator.retrieve_entity(marker, &mut markers, &entities);
+            let ids = |marker: SaveMarker| Some(allocator.retrieve_entity(marker, &mut markers, &entities));
+            let component = match C::convert_from(data, ids) {
+                Ok(component) => component,
+                Err(e) => match e {},
+            };
+            storage.insert(entity, component).expect("Failed to insert loaded component");
+        }
+    }))
+}
+
+/// Give every entity which doesn't have one yet a marker.
+fn mark_all(ecs: &mut World) {
+    let entities = ecs.entities();
+    let mut markers = ecs.write_storage::<SaveMarker>();
+    let mut allocator = ecs.write_resource::<SaveMarkerAllocator>();
+    for entity in entities.join() {
+        allocator.mark(entity, &mut markers);
+    }
+}
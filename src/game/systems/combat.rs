@@ -0,0 +1,80 @@
+use specs::prelude::*;
+
+use crate::game::components;
+use crate::game::Output;
+
+
+/// Resolves attacks, dealing the attacker's attack power less the target's defense as damage,
+/// counting whatever each of them has equipped.
+pub struct CombatSystem;
+
+impl<'a> System<'a> for CombatSystem {
+    type SystemData = (
+        WriteStorage<'a, components::WantsToAttack>,
+        WriteStorage<'a, components::Health>,
+        ReadStorage<'a, components::CombatStats>,
+        ReadStorage<'a, components::Equipped>,
+        ReadStorage<'a, components::Equippable>,
+        ReadStorage<'a, components::InRoom>,
+        ReadStorage<'a, components::Player>,
+        ReadStorage<'a, components::Description>,
+        Entities<'a>,
+        Write<'a, Output>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut wants_to_attack,
+            mut healths,
+            stats,
+            equipped,
+            equippables,
+            inrooms,
+            players,
+            descriptions,
+            entities,
+            mut output,
+        ) = data;
+
+        let glance = |e: Entity| descriptions.get(e).map_or("someone".to_string(), |d| d.glance.clone());
+
+        for (attacker, wants) in (&entities, &wants_to_attack).join() {
+            let target = wants.target;
+
+            // The target may have left, or been taken down, since the attack was made
+            let same_room = match (inrooms.get(attacker), inrooms.get(target)) {
+                (Some(a), Some(t)) => a.room == t.room,
+                _ => false,
+            };
+            let target_up = healths.get(target).is_some_and(|health| health.current > 0);
+            if !same_room || !target_up {
+                if players.contains(attacker) {
+                    output.send(attacker, format!("You swing at the {}, but it's no longer there to hit.", glance(target)));
+                }
+                continue
+            }
+
+            let attack = components::effective_stats(attacker, &stats, &equipped, &equippables).attack;
+            let defense = components::effective_stats(target, &stats, &equipped, &equippables).defense;
+            let damage = (attack - defense).max(0);
+            let health = healths.get_mut(target).unwrap();
+            health.current = (health.current - damage).max(0);
+
+            if players.contains(attacker) {
+                if damage > 0 {
+                    output.send(attacker, format!("You hit the {} for {} damage.", glance(target), damage));
+                } else {
+                    output.send(attacker, format!("You hit the {}, but it shrugs it off.", glance(target)));
+                }
+            }
+            if players.contains(target) {
+                output.send(target, format!(
+                    "The {} hits you for {} damage. ({}/{} health)",
+                    glance(attacker), damage, health.current, health.max,
+                ));
+            }
+        }
+
+        wants_to_attack.clear();
+    }
+}
@@ -0,0 +1,133 @@
+use specs::prelude::*;
+
+use crate::game::components;
+use crate::game::loot;
+use crate::game::map;
+use crate::game::Output;
+use crate::utils;
+
+
+/// Turns everything whose health has run out into a corpse, holding whatever it carried along
+/// with a roll on the loot table it drops.
+///
+/// NPCs and other entities are deleted, while players leave their corpse behind and wake up again
+/// at the spawn point, unhurt and empty-handed.
+pub struct DeathSystem;
+
+impl<'a> System<'a> for DeathSystem {
+    type SystemData = (
+        ReadExpect<'a, map::Map>,
+        WriteStorage<'a, components::Health>,
+        WriteStorage<'a, components::InRoom>,
+        WriteStorage<'a, components::Storage>,
+        WriteStorage<'a, components::Description>,
+        WriteStorage<'a, components::Corpse>,
+        WriteStorage<'a, components::StorageAccess>,
+        WriteStorage<'a, components::Equipped>,
+        ReadStorage<'a, components::Player>,
+        ReadStorage<'a, components::DisplayName>,
+        ReadStorage<'a, components::DropsLoot>,
+        Entities<'a>,
+        Write<'a, Output>,
+        Read<'a, LazyUpdate>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            mut healths,
+            mut inrooms,
+            mut storages,
+            mut descriptions,
+            mut corpses,
+            mut accesses,
+            mut equipped,
+            players,
+            display_names,
+            drops,
+            entities,
+            mut output,
+            lazy,
+        ) = data;
+
+        let dead: Vec<Entity> = (&entities, &healths).join()
+            .filter(|(_, health)| health.current <= 0)
+            .map(|(entity, _)| entity)
+            .collect();
+
+        for entity in dead {
+            let room = match inrooms.get(entity) {
+                Some(inroom) => inroom.room,
+                None => continue,
+            };
+            let glance = descriptions.get(entity).map(|d| d.glance.clone());
+            // Players go by their display name, as they have no description
+            let name = descriptions.get(entity).and_then(|d| d.name.clone())
+                .or_else(|| display_names.get(entity).map(|d| d.name.clone()));
+
+            let witnesses: Vec<Entity> = (&entities, &players, &inrooms).join()
+                .filter(|(e, _, inroom)| *e != entity && inroom.room == room)
+                .map(|(e, _, _)| e)
+                .collect();
+            let who = match (&glance, &name) {
+                (Some(glance), _) => format!("The {}", glance),
+                (None, Some(name)) => name.clone(),
+                (None, None) => "Someone".to_string(),
+            };
+            for witness in witnesses {
+                output.send(witness, format!("{} collapses.", who));
+            }
+
+            // Everything the entity carried or wore goes into its corpse
+            let mut items = storages.get_mut(entity)
+                .map(|store| std::mem::take(&mut store.items))
+                .unwrap_or_default();
+            if let Some(equipment) = equipped.get_mut(entity) {
+                items.extend(std::mem::take(&mut equipment.slots).into_values());
+            }
+            let corpse = entities.create();
+            descriptions.insert(corpse, components::Description {
+                glance: match (&glance, &name) {
+                    (Some(glance), _) => format!("corpse of {}", utils::aan(glance)),
+                    (None, Some(name)) => format!("corpse of {}", name),
+                    (None, None) => "corpse".to_string(),
+                },
+                description: match (&name, &glance) {
+                    (Some(name), _) => format!("The lifeless remains of {}.", name),
+                    (None, Some(glance)) => format!("The lifeless remains of {}.", utils::aan(glance)),
+                    (None, None) => "Lifeless remains.".to_string(),
+                },
+                name: None,
+            }).expect("Failed to insert Description component");
+            inrooms.insert(corpse, components::InRoom { room })
+                .expect("Failed to insert InRoom component");
+            storages.insert(corpse, components::Storage { items })
+                .expect("Failed to insert Storage component");
+            corpses.insert(corpse, components::Corpse)
+                .expect("Failed to insert Corpse component");
+            // Corpses can be looted, but nothing can be put back
+            accesses.insert(corpse, components::StorageAccess { remove_only: true, ..Default::default() })
+                .expect("Failed to insert StorageAccess component");
+
+            // Spawning loot needs the whole world, so it waits until the systems are done
+            if let Some(drops) = drops.get(entity) {
+                let table = drops.table.clone();
+                lazy.exec_mut(move |world| {
+                    if let Err(e) = loot::fill(&table, corpse, world) {
+                        log::error!("Failed to roll loot table {:?} for a corpse: {}", table, e);
+                    }
+                });
+            }
+
+            if players.contains(entity) {
+                let health = healths.get_mut(entity).unwrap();
+                health.current = health.max;
+                inrooms.insert(entity, components::InRoom { room: map.spawn() })
+                    .expect("Failed to insert InRoom component");
+                output.send(entity, "You died! You wake up back where you started, without so much as the clothes on your back.");
+            } else {
+                entities.delete(entity).expect("Failed to delete dead entity");
+            }
+        }
+    }
+}
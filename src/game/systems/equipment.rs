@@ -0,0 +1,92 @@
+use specs::prelude::*;
+
+use crate::game::components;
+use crate::game::{Message, Output};
+
+
+/// Moves things between wearers' inventories and their equipment.
+///
+/// Putting something on in a slot which is already taken takes off whatever was there first.
+pub struct EquipmentSystem;
+
+impl<'a> System<'a> for EquipmentSystem {
+    type SystemData = (
+        WriteStorage<'a, components::ApplyEquipmentChange>,
+        WriteStorage<'a, components::Equipped>,
+        WriteStorage<'a, components::Storage>,
+        ReadStorage<'a, components::Equippable>,
+        ReadStorage<'a, components::Player>,
+        ReadStorage<'a, components::Description>,
+        Entities<'a>,
+        Write<'a, Output>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut changes,
+            mut equipped,
+            mut storages,
+            equippables,
+            players,
+            descriptions,
+            entities,
+            mut output,
+        ) = data;
+
+        let glance = |e: Entity| descriptions.get(e).map_or("something".to_string(), |d| d.glance.clone());
+
+        for (wearer, change) in (&entities, &changes).join() {
+            let item = change.item;
+            let mut messages: Vec<Message> = Vec::new();
+            let (storage, equipment) = match (storages.get_mut(wearer), equipped.get_mut(wearer)) {
+                (Some(storage), Some(equipment)) => (storage, equipment),
+                _ => {
+                    log::error!("Entity {} can't equip anything without Storage and Equipped components", wearer.id());
+                    continue
+                }
+            };
+
+            if change.equip {
+                let slot = match equippables.get(item) {
+                    Some(equippable) => equippable.slot,
+                    None => continue,
+                };
+                if !storage.items.contains(&item) {
+                    messages.push(Message::error(format!("You no longer have the {}.", glance(item))));
+                } else {
+                    storage.items.retain(|i| *i != item);
+                    if let Some(old) = equipment.slots.insert(slot, item) {
+                        storage.items.push(old);
+                        messages.push(format!("You {} the {}.", take_off(slot), glance(old)).into());
+                    }
+                    messages.push(format!("You {} the {}.", put_on(slot), glance(item)).into());
+                }
+            } else {
+                match equipment.slot_of(item) {
+                    Some(slot) => {
+                        equipment.slots.remove(&slot);
+                        storage.items.push(item);
+                        messages.push(format!("You {} the {}.", take_off(slot), glance(item)).into());
+                    }
+                    None => messages.push(Message::error(format!("You aren't using the {}.", glance(item)))),
+                }
+            }
+
+            if players.contains(wearer) {
+                for message in messages {
+                    output.send(wearer, message);
+                }
+            }
+        }
+
+        changes.clear();
+    }
+}
+
+fn put_on(slot: components::Slot) -> &'static str {
+    if slot.is_held() { "wield" } else { "put on" }
+}
+
+fn take_off(slot: components::Slot) -> &'static str {
+    if slot.is_held() { "put away" } else { "take off" }
+}
@@ -1,6 +1,8 @@
 use specs::prelude::*;
 
 use crate::game::components;
+use crate::game::{Message, Output};
+use crate::game::components::StorageAction;
 
 
 pub struct InventorySystem;
@@ -10,7 +12,11 @@ impl<'a> System<'a> for InventorySystem {
         WriteStorage<'a, components::ApplyInventoryChange>,
         WriteStorage<'a, components::Storage>,
         WriteStorage<'a, components::InRoom>,
+        ReadStorage<'a, components::Player>,
+        ReadStorage<'a, components::Description>,
+        ReadStorage<'a, components::StorageAccess>,
         Entities<'a>,
+        Write<'a, Output>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -18,14 +24,50 @@ impl<'a> System<'a> for InventorySystem {
             mut changes,
             mut storages,
             mut inrooms,
+            players,
+            descriptions,
+            accesses,
             entities,
+            mut output,
         ) = data;
 
+        let glance = |e: Entity| descriptions.get(e).map_or("something".to_string(), |d| d.glance.clone());
+
         for (entity, change) in (&entities, &changes).join() {
+            // Used up or otherwise gone since the change was made
+            if !entities.is_alive(entity) {
+                continue
+            }
+
+            // Whoever is moving the item has to be allowed to take it out and put it in, unless
+            // they're stealing it
+            let refusal = change.from_container
+                .and_then(|c| accesses.get(c).and_then(|a| a.check(change.by, StorageAction::Take, &glance(c)).err()))
+                .or_else(|| change.to_container
+                    .and_then(|c| accesses.get(c).and_then(|a| a.check(change.by, StorageAction::Put, &glance(c)).err())))
+                .filter(|_| !change.stolen);
+            if let Some(refusal) = refusal {
+                if players.contains(change.by) {
+                    output.send(change.by, Message::error(refusal));
+                }
+                continue
+            }
+
+            // Something else may have moved the item out of the container first, like its owner dying
+            let gone = change.from_container
+                .filter(|c| storages.get(*c).is_some_and(|store| !store.items.contains(&entity)));
+            if let Some(from_container) = gone {
+                if players.contains(change.by) {
+                    let moved = format!("The {} is no longer in the {}.", glance(entity), glance(from_container));
+                    output.send(change.by, Message::error(moved));
+                }
+                continue
+            }
+
             match (change.from_container, change.to_container) {
                 (Some(from_container), Some(to_container)) => {
                     if storages.get(from_container).is_none() || storages.get(to_container).is_none() {
-                        println!("Error: Failed to move item {} between containers because one \
+                        log::error!("Failed to move item {} between containers because one \
                         or both containers are missing the Storage component.", entity.id());
                         return
                     }
@@ -33,33 +75,49 @@ impl<'a> System<'a> for InventorySystem {
                     from_store.items.retain(|item| {*item != entity});
                     let to_store = storages.get_mut(to_container).unwrap();
                     to_store.items.push(entity);
+
+                    if players.contains(to_container) && change.stolen {
+                        output.send(to_container, format!("You slip the {} away from the {}.", glance(entity), glance(from_container)));
+                    } else if players.contains(to_container) {
+                        output.send(to_container, format!("You take the {} from the {}.", glance(entity), glance(from_container)));
+                    } else if players.contains(from_container) {
+                        output.send(from_container, format!("You put the {} in the {}.", glance(entity), glance(to_container)));
+                    }
                 }
                 (Some(from_container), None) => {
                     if inrooms.get(from_container).is_none() {
-                        println!("Error: Failed to drop item {} because its container {} is not \
+                        log::error!("Failed to drop item {} because its container {} is not \
                         attached to any room.", entity.id(), from_container.id());
                         return
                     }
                     let inroom = inrooms.get(from_container)
-                        .expect(format!("Failed to drop item {} because it already is attached to a room", entity.id()).as_str());
-                    let room = inroom.room.clone();
+                        .unwrap_or_else(|| panic!("Failed to drop item {} because it already is attached to a room", entity.id()));
+                    let room = inroom.room;
                     let from_store = storages.get_mut(from_container)
-                        .expect(format!("Expected entity {} to have a storage component", from_container.id()).as_str());
+                        .unwrap_or_else(|| panic!("Expected entity {} to have a storage component", from_container.id()));
                     from_store.items.retain(|item| { *item != entity });
                     inrooms.insert(entity, components::InRoom { room })
                         .expect("Failed to insert InRoom component");
+
+                    if players.contains(from_container) {
+                        output.send(from_container, format!("You drop the {}.", glance(entity)));
+                    }
                 }
                 (None, Some(to_container)) => {
                     let _ = inrooms.get(entity)
-                        .expect(format!("Failed to pick up item {} because it is not attached to a room", entity.id()).as_str());
+                        .unwrap_or_else(|| panic!("Failed to pick up item {} because it is not attached to a room", entity.id()));
                     let to_store = storages.get_mut(to_container)
-                        .expect(format!("Expected entity {} to have a storage component", to_container.id()).as_str());
+                        .unwrap_or_else(|| panic!("Expected entity {} to have a storage component", to_container.id()));
                     inrooms.remove(entity)
                         .expect("Failed to remove InRoom component");
                     to_store.items.push(entity);
+
+                    if players.contains(to_container) {
+                        output.send(to_container, format!("You pick up the {}.", glance(entity)));
+                    }
                 }
                 (None, None) => {
-                    println!("Error: WTF am I supposed to do with this case?");
+                    log::error!("WTF am I supposed to do with this case?");
                 }
             }
         }
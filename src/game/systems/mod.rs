@@ -1,5 +1,17 @@
 mod movement;
 mod inventory;
+mod combat;
+mod equipment;
+mod death;
+mod npc;
+mod theft;
+mod usage;
 
 pub use movement::MovementSystem;
 pub use inventory::InventorySystem;
+pub use combat::CombatSystem;
+pub use equipment::EquipmentSystem;
+pub use death::DeathSystem;
+pub use npc::NpcSystem;
+pub use theft::TheftSystem;
+pub use usage::UseSystem;
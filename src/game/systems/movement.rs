@@ -2,6 +2,7 @@ use specs::prelude::*;
 
 use crate::game::map;
 use crate::game::components;
+use crate::game::Output;
 
 
 pub struct MovementSystem;
@@ -12,7 +13,11 @@ impl<'a> System<'a> for MovementSystem {
         WriteStorage<'a, components::ApplyMove>,
         WriteStorage<'a, components::InRoom>,
         ReadStorage<'a, components::Description>,
+        ReadStorage<'a, components::DisplayName>,
+        ReadStorage<'a, components::Player>,
+        WriteStorage<'a, components::Journal>,
         Entities<'a>,
+        Write<'a, Output>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -21,23 +26,35 @@ impl<'a> System<'a> for MovementSystem {
             mut apply_moves,
             mut inrooms,
             descriptions,
+            names,
+            players,
+            mut journals,
             entities,
+            mut output,
         ) = data;
 
-        let mut new_room: Option<map::RoomId> = None;
-        for (move_, inroom) in (&apply_moves, &mut inrooms).join() {
-            inroom.room = move_.room.clone();
-            new_room = Some(inroom.room.clone());
+        let mut moved: Vec<(Entity, map::RoomId)> = Vec::new();
+        for (entity, move_, inroom) in (&entities, &apply_moves, &mut inrooms).join() {
+            inroom.room = move_.room;
+            if let Some(journal) = journals.get_mut(entity) {
+                journal.visited.insert(inroom.room);
+            }
+            moved.push((entity, inroom.room));
         }
         apply_moves.clear();
 
-        // TODO(networking): Rework this for multiple players
-        if let Some(id) = new_room {
+        // Only the player who moved gets to see where they ended up
+        for (entity, id) in moved {
+            if !players.contains(entity) {
+                continue
+            }
             let room = map.room(&id);
-            println!("{}", room.description(
+            output.send(entity, room.description(
+                entity,
                 &entities,
                 &mut inrooms,
                 &descriptions,
+                &names,
             ));
         }
     }
@@ -0,0 +1,165 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use specs::prelude::*;
+
+use crate::game::components;
+use crate::game::components::StorageAction;
+use crate::game::map::{self, ExitDirection};
+use crate::game::{Output, RunState};
+
+
+/// Something an NPC has decided to do.
+enum Action {
+    Emote(String),
+    Wander(ExitDirection, map::RoomId),
+    Rummage(Entity),
+}
+
+/// Has NPCs act out their behaviours during the NPC turn, telling players in the same room.
+pub struct NpcSystem;
+
+impl<'a> System<'a> for NpcSystem {
+    type SystemData = (
+        ReadExpect<'a, RunState>,
+        ReadExpect<'a, map::Map>,
+        ReadStorage<'a, components::Behaviour>,
+        ReadStorage<'a, components::InRoom>,
+        ReadStorage<'a, components::Description>,
+        ReadStorage<'a, components::Storage>,
+        ReadStorage<'a, components::StorageAccess>,
+        ReadStorage<'a, components::Npc>,
+        ReadStorage<'a, components::Player>,
+        ReadStorage<'a, components::Health>,
+        WriteStorage<'a, components::Hostile>,
+        WriteStorage<'a, components::ApplyMove>,
+        WriteStorage<'a, components::WantsToAttack>,
+        Entities<'a>,
+        Write<'a, Output>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            runstate,
+            map,
+            behaviours,
+            inrooms,
+            descriptions,
+            storages,
+            accesses,
+            npcs,
+            players,
+            healths,
+            mut hostiles,
+            mut apply_moves,
+            mut wants_to_attack,
+            entities,
+            mut output,
+        ) = data;
+
+        if *runstate != RunState::NpcTurn {
+            return
+        }
+
+        let mut rng = rand::thread_rng();
+        let glance = |e: Entity| descriptions.get(e).map_or("someone".to_string(), |d| d.glance.clone());
+        let players_in = |room: map::RoomId| -> Vec<Entity> {
+            (&entities, &players, &inrooms).join()
+                .filter(|(_, _, inroom)| inroom.room == room)
+                .map(|(e, _, _)| e)
+                .collect()
+        };
+
+        // NPCs with a grudge go after whoever they hold it against, and do nothing else meanwhile
+        let mut forgotten: Vec<Entity> = Vec::new();
+        let mut busy: Vec<Entity> = Vec::new();
+        for (npc, hostile, inroom) in (&entities, &hostiles, &inrooms).join() {
+            if !entities.is_alive(hostile.target) {
+                forgotten.push(npc);
+                continue
+            }
+            let target_here = inrooms.get(hostile.target).is_some_and(|target| target.room == inroom.room);
+            let target_up = healths.get(hostile.target).is_some_and(|health| health.current > 0);
+            if target_here && target_up {
+                wants_to_attack.insert(npc, components::WantsToAttack { target: hostile.target })
+                    .expect("Unable to insert WantsToAttack");
+                busy.push(npc);
+            }
+        }
+        for npc in forgotten {
+            hostiles.remove(npc);
+        }
+
+        for (npc, behaviour, inroom) in (&entities, &behaviours, &inrooms).join() {
+            if busy.contains(&npc) {
+                continue
+            }
+            if !rng.gen_bool(behaviour.chance.clamp(0.0, 1.0)) {
+                continue
+            }
+            // Nobody would notice the NPC doing anything
+            let room = inroom.room;
+            let witnesses = players_in(room);
+            if witnesses.is_empty() {
+                continue
+            }
+
+            let mut actions: Vec<Action> = behaviour.emotes.iter()
+                .map(|emote| Action::Emote(emote.clone()))
+                .collect();
+            if behaviour.wanders {
+                actions.extend(map.room(&room).exits.iter()
+                    .filter(|(_, gate)| !gate.is_closed() && !gate.is_locked())
+                    .map(|(dir, gate)| Action::Wander(dir.clone(), gate.to())));
+            }
+            if behaviour.rummages {
+                actions.extend((&entities, &storages, &inrooms).join()
+                    .filter(|(e, _, container_room)| {
+                        container_room.room == room && !players.contains(*e) && !npcs.contains(*e)
+                    })
+                    .filter(|(e, _, _)| {
+                        accesses.get(*e).is_none_or(|access| access.check(npc, StorageAction::Look, "").is_ok())
+                    })
+                    .map(|(e, _, _)| Action::Rummage(e)));
+            }
+
+            let who = glance(npc);
+            match actions.choose(&mut rng) {
+                Some(Action::Emote(emote)) => {
+                    for player in witnesses {
+                        output.send(player, format!("The {} {}", who, emote));
+                    }
+                }
+                Some(Action::Wander(dir, to)) => {
+                    apply_moves.insert(npc, components::ApplyMove { room: *to })
+                        .expect("Unable to insert ApplyMove");
+                    for player in witnesses {
+                        output.send(player, format!("The {} leaves {}.", who, heading(dir)));
+                    }
+                    for player in players_in(*to) {
+                        output.send(player, format!("The {} arrives.", who));
+                    }
+                }
+                Some(Action::Rummage(container)) => {
+                    for player in witnesses {
+                        output.send(player, format!("The {} opens the {} and looks inside.", who, glance(*container)));
+                    }
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+/// Describe which way someone is heading through an exit.
+fn heading(dir: &ExitDirection) -> String {
+    match dir {
+        ExitDirection::Next => "for the next car".to_string(),
+        ExitDirection::Previous => "for the previous car".to_string(),
+        ExitDirection::Up => "upwards".to_string(),
+        ExitDirection::Down => "downwards".to_string(),
+        ExitDirection::In => "inside".to_string(),
+        ExitDirection::Out => "outside".to_string(),
+        ExitDirection::Named(name) => format!("by the {}", name),
+        dir => dir.name().to_string(),
+    }
+}
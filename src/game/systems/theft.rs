@@ -0,0 +1,146 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use specs::prelude::*;
+
+use crate::game::components;
+use crate::game::{Message, Output, RunState};
+
+
+/// How aware NPCs are of their pockets, unless they say otherwise.
+const DEFAULT_AWARENESS: i32 = 3;
+
+/// How many NPC turns an NPC keeps an eye on its belongings after someone steals from it, and
+/// after catching someone at it.
+const COOLDOWN: u32 = 5;
+const CAUGHT_COOLDOWN: u32 = 10;
+
+/// Resolves attempts to steal from NPCs, pitting the thief's stealth against the NPC's awareness.
+///
+/// Successful thefts move the item over with an `ApplyInventoryChange`. NPCs who catch a thief
+/// alert the room, and turn on them if they can fight.
+pub struct TheftSystem;
+
+impl<'a> System<'a> for TheftSystem {
+    type SystemData = (
+        ReadExpect<'a, RunState>,
+        WriteStorage<'a, components::WantsToSteal>,
+        WriteStorage<'a, components::ApplyInventoryChange>,
+        WriteStorage<'a, components::Wary>,
+        WriteStorage<'a, components::Hostile>,
+        ReadStorage<'a, components::Storage>,
+        ReadStorage<'a, components::Stealth>,
+        ReadStorage<'a, components::Awareness>,
+        ReadStorage<'a, components::CombatStats>,
+        ReadStorage<'a, components::InRoom>,
+        ReadStorage<'a, components::Player>,
+        ReadStorage<'a, components::Description>,
+        ReadStorage<'a, components::DisplayName>,
+        Entities<'a>,
+        Write<'a, Output>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            runstate,
+            mut wants_to_steal,
+            mut changes,
+            mut wary,
+            mut hostiles,
+            storages,
+            stealths,
+            awarenesses,
+            stats,
+            inrooms,
+            players,
+            descriptions,
+            names,
+            entities,
+            mut output,
+        ) = data;
+
+        // NPCs let their guard down again over time
+        if *runstate == RunState::NpcTurn {
+            let calmed: Vec<Entity> = (&entities, &mut wary).join()
+                .filter_map(|(npc, wary)| {
+                    wary.turns = wary.turns.saturating_sub(1);
+                    (wary.turns == 0).then_some(npc)
+                })
+                .collect();
+            for npc in calmed {
+                wary.remove(npc);
+            }
+        }
+
+        let mut rng = rand::thread_rng();
+        let glance = |e: Entity| descriptions.get(e).map_or("someone".to_string(), |d| d.glance.clone());
+
+        for (thief, wants) in (&entities, &wants_to_steal).join() {
+            let npc = wants.from;
+            let room = match (inrooms.get(thief), inrooms.get(npc)) {
+                (Some(a), Some(b)) if a.room == b.room => a.room,
+                _ => {
+                    output.send(thief, format!("The {} is no longer around to steal from.", glance(npc)));
+                    continue
+                }
+            };
+            let pockets = storages.get(npc).map_or(&[][..], |storage| storage.items.as_slice());
+            let item = match wants.item {
+                Some(item) if !pockets.contains(&item) => {
+                    output.send(thief, format!("The {} no longer has the {}.", glance(npc), glance(item)));
+                    continue
+                }
+                // Someone else got to it first this turn
+                Some(item) if changes.contains(item) => {
+                    output.send(thief, Message::error(format!("The {} is already being moved.", glance(item))));
+                    continue
+                }
+                Some(item) => Some(item),
+                None => {
+                    let free: Vec<Entity> = pockets.iter().copied().filter(|item| !changes.contains(*item)).collect();
+                    free.choose(&mut rng).copied()
+                }
+            };
+
+            let stealth = stealths.get(thief).map_or(0, |s| s.skill);
+            let awareness = awarenesses.get(npc).map_or(DEFAULT_AWARENESS, |a| a.level);
+            let chance = (0.5 + 0.1 * (stealth - awareness) as f64).clamp(0.05, 0.95);
+            if rng.gen_bool(chance) {
+                wary.insert(npc, components::Wary { turns: COOLDOWN })
+                    .expect("Unable to insert Wary");
+                match item {
+                    Some(item) => {
+                        changes.insert(item, components::ApplyInventoryChange {
+                            from_container: Some(npc),
+                            to_container: Some(thief),
+                            by: thief,
+                            stolen: true,
+                        }).expect("Unable to insert ApplyInventoryChange");
+                    }
+                    None => {
+                        let empty = format!("You go through the {}'s pockets unnoticed, but come up empty-handed.", glance(npc));
+                        output.send(thief, empty);
+                    }
+                }
+                continue
+            }
+
+            // Caught in the act, in front of everyone
+            wary.insert(npc, components::Wary { turns: CAUGHT_COOLDOWN })
+                .expect("Unable to insert Wary");
+            output.send(thief, Message::error(format!("The {} catches you with your hand in its pocket!", glance(npc))));
+            let name = names.get(thief).map_or("someone".to_string(), |d| d.name.clone());
+            for (witness, _, inroom) in (&entities, &players, &inrooms).join() {
+                if witness != thief && inroom.room == room {
+                    output.send(witness, format!("The {} shouts, \"Thief!\" and points at {}.", glance(npc), name));
+                }
+            }
+            if stats.contains(npc) {
+                hostiles.insert(npc, components::Hostile { target: thief })
+                    .expect("Unable to insert Hostile");
+                output.send(thief, format!("The {} turns on you!", glance(npc)));
+            }
+        }
+
+        wants_to_steal.clear();
+    }
+}
@@ -0,0 +1,202 @@
+use specs::prelude::*;
+
+use crate::game::components::{self, UseEffect, UseTarget};
+use crate::game::content;
+use crate::game::map::Map;
+use crate::game::{Message, Output};
+
+
+/// Resolves players using things, applying the effects of whatever they use.
+///
+/// Effects which turn the item into something else, or use it up, only happen when the rest of
+/// its effects worked: a tonic isn't drunk when nobody needed healing.
+pub struct UseSystem;
+
+impl<'a> System<'a> for UseSystem {
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        WriteStorage<'a, components::WantsToUse>,
+        WriteStorage<'a, components::Storage>,
+        WriteStorage<'a, components::Health>,
+        WriteStorage<'a, components::Description>,
+        ReadStorage<'a, components::Usable>,
+        ReadStorage<'a, components::InRoom>,
+        ReadStorage<'a, components::ApplyInventoryChange>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+        Write<'a, Output>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut map,
+            mut wants_to_use,
+            mut storages,
+            mut healths,
+            mut descriptions,
+            usables,
+            inrooms,
+            changes,
+            lazy,
+            entities,
+            mut output,
+        ) = data;
+
+        for (user, wants) in (&entities, &wants_to_use).join() {
+            let item = wants.item;
+            let glance = |ds: &WriteStorage<components::Description>, e: Entity| {
+                ds.get(e).map_or("something".to_string(), |d| d.glance.clone())
+            };
+            let item_glance = glance(&descriptions, item);
+
+            // Someone may have made off with it since
+            let container = (&entities, &storages).join()
+                .find(|(_, storage)| storage.items.contains(&item))
+                .map(|(e, _)| e);
+            let room = inrooms.get(user).map(|inroom| inroom.room);
+            let in_reach = container == Some(user) || (inrooms.get(item).map(|inroom| inroom.room) == room && room.is_some());
+            let usable = match usables.get(item) {
+                Some(usable) if in_reach && entities.is_alive(item) => usable,
+                _ => {
+                    output.send(user, Message::error(format!("The {} is no longer within reach.", item_glance)));
+                    continue
+                }
+            };
+            // Using it could use it up from under whoever is moving it
+            if changes.contains(item) {
+                output.send(user, Message::error(format!("The {} is already being moved.", item_glance)));
+                continue
+            }
+
+            // Every effect either works, saying what happened, or gives the reason it didn't
+            let mut results: Vec<Result<String, String>> = Vec::new();
+            for effect in &usable.effects {
+                let result = match (effect, &wants.target) {
+                    (UseEffect::Heal(amount), target) => {
+                        let target = match target {
+                            Some(UseTarget::Entity(target)) => *target,
+                            Some(UseTarget::Exit(dir)) => {
+                                results.push(Err(format!("Nothing happens to the {} exit.", dir.name())));
+                                continue
+                            }
+                            None => user,
+                        };
+                        let target_glance = glance(&descriptions, target);
+                        match healths.get_mut(target) {
+                            None => Err(format!("The {} can't be healed.", target_glance)),
+                            Some(health) if health.current >= health.max => {
+                                if target == user {
+                                    Err("You're already unhurt.".to_string())
+                                } else {
+                                    Err(format!("The {} is already unhurt.", target_glance))
+                                }
+                            }
+                            Some(health) => {
+                                let healed = (*amount).min(health.max - health.current);
+                                health.current += healed;
+                                if target == user {
+                                    Ok(format!("You recover {} health. ({}/{} health)", healed, health.current, health.max))
+                                } else {
+                                    Ok(format!("The {} recovers {} health.", target_glance, healed))
+                                }
+                            }
+                        }
+                    }
+                    (UseEffect::Unlock, Some(UseTarget::Exit(dir))) => {
+                        let room = room.expect("Expected player to be in a room");
+                        match map.room(&room).exit(dir.clone()) {
+                            None => Err(format!("There's no {} exit here", dir.name())),
+                            Some(gate) if !gate.has_lock() => Err("There's no lock on it.".to_string()),
+                            Some(gate) if !gate.is_locked() => Err("It's already unlocked.".to_string()),
+                            Some(gate) if !gate.fits(item) => Err(format!("The {} doesn't fit.", item_glance)),
+                            Some(_) => {
+                                map.update_gate(&room, dir, |gate| gate.set_locked(false));
+                                Ok(format!("You unlock the {} exit with the {}.", dir.name(), item_glance))
+                            }
+                        }
+                    }
+                    (UseEffect::Unlock, Some(UseTarget::Entity(target))) => {
+                        Err(format!("The {} has no lock.", glance(&descriptions, *target)))
+                    }
+                    (UseEffect::Unlock, None) => Err(format!("Use the {} on what?", item_glance)),
+                    (UseEffect::Reveal(text), target) => {
+                        let target = match target {
+                            Some(UseTarget::Entity(target)) => *target,
+                            Some(UseTarget::Exit(dir)) => {
+                                results.push(Err(format!("Nothing happens to the {} exit.", dir.name())));
+                                continue
+                            }
+                            None => item,
+                        };
+                        match descriptions.get_mut(target) {
+                            Some(description) => {
+                                description.description = text.clone();
+                                Ok(text.clone())
+                            }
+                            None => Err("Nothing happens.".to_string()),
+                        }
+                    }
+                    (UseEffect::Transform(_), _) | (UseEffect::Consume, _) => continue,
+                };
+                results.push(result);
+            }
+
+            if !results.is_empty() && results.iter().all(Result::is_err) {
+                let reason = results.into_iter().find_map(Result::err).unwrap();
+                output.send(user, Message::error(reason));
+                continue
+            }
+
+            match &usable.message {
+                Some(message) => output.send(user, message.clone()),
+                None if results.is_empty() => output.send(user, format!("You use the {}.", item_glance)),
+                None => {}
+            }
+            for line in results.into_iter().flatten() {
+                output.send(user, line);
+            }
+
+            let transform = usable.effects.iter().find_map(|effect| match effect {
+                UseEffect::Transform(prefab) => Some(prefab.clone()),
+                _ => None,
+            });
+            let consumed = usable.effects.iter().any(|effect| matches!(effect, UseEffect::Consume));
+            if transform.is_none() && !consumed {
+                continue
+            }
+
+            // Used up, or turned into something else in its place
+            let place = inrooms.get(item).map(|inroom| inroom.room);
+            if let Some(storage) = container.and_then(|container| storages.get_mut(container)) {
+                storage.items.retain(|i| *i != item);
+            }
+            entities.delete(item).expect("Failed to delete used up item");
+            if let Some(prefab) = transform {
+                lazy.exec_mut(move |ecs| {
+                    let spawned = match place {
+                        Some(room) if container.is_none() => content::spawn_prefab(&prefab, room, ecs),
+                        _ => content::spawn_detached(&prefab, ecs),
+                    };
+                    let new = match spawned {
+                        Ok(new) => new,
+                        Err(e) => {
+                            log::warn!("Failed to turn item {} into a {:?}: {}", item.id(), prefab, e);
+                            return
+                        }
+                    };
+                    if let Some(container) = container {
+                        if let Some(storage) = ecs.write_storage::<components::Storage>().get_mut(container) {
+                            storage.items.push(new);
+                        }
+                    }
+                    let new_glance = ecs.read_storage::<components::Description>()
+                        .get(new)
+                        .map_or("something".to_string(), |d| d.glance.clone());
+                    ecs.write_resource::<Output>().send(user, format!("The {} turns into a {}.", item_glance, new_glance));
+                });
+            }
+        }
+
+        wants_to_use.clear();
+    }
+}
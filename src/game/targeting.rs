@@ -0,0 +1,126 @@
+// Working out which entity a player means.
+//
+// Players refer to things the way they are described: by glance ("robot pirate"), by name
+// ("Clarice Nimpton"), or by only some of the words ("robot", "blue key", "old"). Every word the
+// player gives has to start one of the words of the glance, or of the name. When several things
+// match, an ordinal picks one of them: "2.robot", "2nd robot" or "second robot pirate".
+// ----------------------------
+use specs::Entity;
+use crate::game::components::Description;
+
+
+const ORDINALS: [&str; 10] = [
+    "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth", "tenth",
+];
+
+/// What a player's description of an entity turned out to mean.
+#[derive(Debug, PartialEq)]
+pub enum Target {
+    Found(Entity),
+    NotFound,
+    // Several different things match, listed as phrases which pick out each of them.
+    Ambiguous(Vec<String>),
+}
+
+/// A description of an entity, split up into the words to match and which of the matches to take.
+struct NounPhrase {
+    index: Option<usize>,
+    words: Vec<String>,
+}
+
+impl NounPhrase {
+    fn parse(phrase: &str) -> Self {
+        let phrase = phrase.trim().to_lowercase();
+
+        // "2.robot"
+        if let Some((number, rest)) = phrase.split_once('.') {
+            if let Ok(index) = number.trim().parse::<usize>() {
+                return NounPhrase { index: Some(index), words: split_words(rest) }
+            }
+        }
+
+        let mut words = split_words(&phrase);
+        let index = words.first().and_then(|word| parse_ordinal(word));
+        if index.is_some() && words.len() > 1 {
+            words.remove(0);
+            NounPhrase { index, words }
+        } else {
+            NounPhrase { index: None, words }
+        }
+    }
+
+    fn matches(&self, description: &Description) -> bool {
+        let glance = split_words(&description.glance);
+        let name = description.name.as_deref().map(split_words).unwrap_or_default();
+        !self.words.is_empty() && (matches_words(&self.words, &glance) || matches_words(&self.words, &name))
+    }
+}
+
+/// Find the entity a phrase refers to among the candidates, in the order they are given.
+pub fn resolve<'a, I>(phrase: &str, candidates: I) -> Target
+where
+    I: IntoIterator<Item = (Entity, &'a Description)>,
+{
+    let phrase = NounPhrase::parse(phrase);
+    let matches: Vec<(Entity, &Description)> = candidates.into_iter()
+        .filter(|(_, description)| phrase.matches(description))
+        .collect();
+
+    match phrase.index {
+        Some(index) => match index.checked_sub(1).and_then(|i| matches.get(i)) {
+            Some((entity, _)) => Target::Found(*entity),
+            None => Target::NotFound,
+        },
+        None => match matches.as_slice() {
+            [] => Target::NotFound,
+            [(entity, _)] => Target::Found(*entity),
+            [(first, first_description), rest @ ..] => {
+                // There is no telling identical things apart, and no need to either
+                let identical = rest.iter().all(|(_, description)| {
+                    description.glance == first_description.glance && description.name == first_description.name
+                });
+                if identical {
+                    return Target::Found(*first)
+                }
+
+                let words = phrase.words.join(" ");
+                Target::Ambiguous(matches.iter().enumerate()
+                    .map(|(i, (_, description))| match &description.name {
+                        Some(name) => format!("{}.{} ({})", i + 1, words, name),
+                        None => format!("{}.{} ({})", i + 1, words, description.glance),
+                    })
+                    .collect())
+            }
+        },
+    }
+}
+
+fn split_words(text: &str) -> Vec<String> {
+    text.split_whitespace().map(|word| word.to_lowercase()).collect()
+}
+
+/// Parse "second" or "2nd" into 2.
+fn parse_ordinal(word: &str) -> Option<usize> {
+    if let Some(i) = ORDINALS.iter().position(|ordinal| *ordinal == word) {
+        return Some(i + 1)
+    }
+    let digits = word.strip_suffix("st")
+        .or_else(|| word.strip_suffix("nd"))
+        .or_else(|| word.strip_suffix("rd"))
+        .or_else(|| word.strip_suffix("th"))?;
+    digits.parse().ok()
+}
+
+/// Check every word starts a different one of the candidate words.
+fn matches_words(words: &[String], candidate: &[String]) -> bool {
+    let mut unused: Vec<&String> = candidate.iter().collect();
+    words.iter().all(|word| {
+        match unused.iter().position(|c| c.starts_with(word.as_str())) {
+            Some(i) => {
+                unused.remove(i);
+                true
+            }
+            None => false,
+        }
+    })
+}
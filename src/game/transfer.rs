@@ -0,0 +1,154 @@
+// Moving players between worlds.
+//
+// A player leaving a world is packed up into a `PlayerState`, which holds everything about them
+// that should survive the trip, and their entities are removed. Unpacking the state into another
+// world recreates them there.
+// ----------------------------
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use crate::game::accounts;
+use crate::game::components;
+use crate::game::map::RoomId;
+
+
+/// Everything about a player which survives moving them to another world.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PlayerState {
+    pub inventory: Vec<ItemState>,
+    // Missing for players who never had any, who start out unhurt.
+    #[serde(default)]
+    pub health: Option<components::Health>,
+    // Flags and quest stages, which mean the same in every world. Visited rooms don't.
+    #[serde(default)]
+    pub journal: Option<components::Journal>,
+    // What the player was wearing and wielding, which they put back on at the other end.
+    #[serde(default)]
+    pub equipment: Vec<ItemState>,
+}
+
+/// An item carried by a player, along with anything stored inside of it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ItemState {
+    pub description: String,
+    pub glance: String,
+    pub name: Option<String>,
+    pub contents: Option<Vec<ItemState>>,
+    #[serde(default)]
+    pub usable: Option<components::Usable>,
+    #[serde(default)]
+    pub equippable: Option<components::Equippable>,
+}
+
+/// Pack up a player, then delete them and everything they carry from the world.
+pub fn remove_player(player: Entity, ecs: &mut World) -> PlayerState {
+    let mut doomed: Vec<Entity> = vec![player];
+    let (inventory, equipment) = {
+        let storages = ecs.read_storage::<components::Storage>();
+        let descriptions = ecs.read_storage::<components::Description>();
+        let usables = ecs.read_storage::<components::Usable>();
+        let equippables = ecs.read_storage::<components::Equippable>();
+        let items = storages.get(player).map(|store| store.items.clone()).unwrap_or_default();
+        let equipped: Vec<Entity> = ecs.read_storage::<components::Equipped>().get(player)
+            .map(|equipped| equipped.slots.values().copied().collect())
+            .unwrap_or_default();
+        (
+            pack_items(&items, &storages, &descriptions, &usables, &equippables, &mut doomed),
+            pack_items(&equipped, &storages, &descriptions, &usables, &equippables, &mut doomed),
+        )
+    };
+    let health = ecs.read_storage::<components::Health>().get(player).cloned();
+    let journal = ecs.read_storage::<components::Journal>().get(player)
+        .map(|journal| components::Journal { visited: Default::default(), ..journal.clone() });
+
+    ecs.write_resource::<accounts::Accounts>().unregister(player);
+    ecs.delete_entities(&doomed).expect("Failed to delete player entities");
+    ecs.maintain();
+    PlayerState { inventory, health, journal, equipment }
+}
+
+/// Create a player from a packed up state, placing them in the given room.
+pub fn add_player(state: &PlayerState, room: RoomId, ecs: &mut World) -> Entity {
+    let mut items = unpack_items(&state.inventory, ecs);
+    let mut equipment = components::Equipped::new();
+    for (packed, item) in state.equipment.iter().zip(unpack_items(&state.equipment, ecs)) {
+        // Anything which can't go back on, or whose slot is already taken, is carried instead
+        match packed.equippable.as_ref().map(|equippable| equippable.slot) {
+            Some(slot) if !equipment.slots.contains_key(&slot) => {
+                equipment.slots.insert(slot, item);
+            }
+            _ => items.push(item),
+        }
+    }
+    let player = accounts::create_character(room, ecs);
+    ecs.write_storage::<components::Storage>()
+        .insert(player, components::Storage { items })
+        .expect("Failed to insert Storage component");
+    ecs.write_storage::<components::Equipped>()
+        .insert(player, equipment)
+        .expect("Failed to insert Equipped component");
+    if let Some(health) = &state.health {
+        ecs.write_storage::<components::Health>()
+            .insert(player, health.clone())
+            .expect("Failed to insert Health component");
+    }
+    if let Some(journal) = &state.journal {
+        let mut journals = ecs.write_storage::<components::Journal>();
+        if let Some(current) = journals.get_mut(player) {
+            current.flags = journal.flags.clone();
+            current.quests = journal.quests.clone();
+        }
+    }
+    player
+}
+
+fn pack_items(
+    items: &[Entity],
+    storages: &ReadStorage<components::Storage>,
+    descriptions: &ReadStorage<components::Description>,
+    usables: &ReadStorage<components::Usable>,
+    equippables: &ReadStorage<components::Equippable>,
+    doomed: &mut Vec<Entity>,
+) -> Vec<ItemState> {
+    let mut packed: Vec<ItemState> = Vec::new();
+    for item in items {
+        doomed.push(*item);
+        let description = descriptions.get(*item)
+            .unwrap_or_else(|| panic!("Expected item {} to have a Description component!", item.id()));
+        packed.push(ItemState {
+            description: description.description.clone(),
+            glance: description.glance.clone(),
+            name: description.name.clone(),
+            contents: storages.get(*item)
+                .map(|store| pack_items(&store.items, storages, descriptions, usables, equippables, doomed)),
+            usable: usables.get(*item).cloned(),
+            equippable: equippables.get(*item).cloned(),
+        });
+    }
+    packed
+}
+
+fn unpack_items(items: &[ItemState], ecs: &mut World) -> Vec<Entity> {
+    let mut unpacked: Vec<Entity> = Vec::new();
+    for item in items {
+        let contents = item.contents.as_ref()
+            .map(|contents| unpack_items(contents, ecs));
+        let mut builder = ecs.create_entity()
+            .with(components::Item{})
+            .with(components::Description {
+                description: item.description.clone(),
+                glance: item.glance.clone(),
+                name: item.name.clone(),
+            });
+        if let Some(items) = contents {
+            builder = builder.with(components::Storage { items });
+        }
+        if let Some(usable) = &item.usable {
+            builder = builder.with(usable.clone());
+        }
+        if let Some(equippable) = &item.equippable {
+            builder = builder.with(equippable.clone());
+        }
+        unpacked.push(builder.build());
+    }
+    unpacked
+}
@@ -0,0 +1,170 @@
+// Shared plumbing for the front-ends which host a single game for many connections.
+//
+// Connections are read on their own threads and report what happens on them as events to the
+// thread which owns the game. A new connection is asked for a character name first, which is the
+// account it logs in to, and everything after that is player input for the character.
+// ----------------------------
+use std::collections::HashMap;
+use std::sync::mpsc;
+
+use specs::world::Index as EntityId;
+
+use crate::game::{Game, Message, OutputSink};
+
+pub type ConnectionId = u64;
+
+// Longest character name accepted at login.
+const MAX_NAME_LENGTH: usize = 20;
+
+pub enum Event<C> {
+    // A new connection, along with whatever the front-end needs to write to it.
+    Connected(ConnectionId, C),
+
+    // A line of input from a connection. Until the connection has logged in, this is the name
+    // of the character to log in as.
+    Line(ConnectionId, String),
+
+    // An explicit request to log in as a character.
+    Login(ConnectionId, String),
+
+    // A command from a connection which logs in with `Login`, never with a line of input.
+    Command(ConnectionId, String),
+
+    // The connection was closed.
+    Disconnected(ConnectionId),
+}
+
+/// The open connections of a front-end, which the game's output is delivered to.
+pub trait Connections {
+    /// Whatever the front-end needs to write to a connection.
+    type Client;
+
+    fn add(&mut self, connection: ConnectionId, client: Self::Client);
+
+    fn remove(&mut self, connection: ConnectionId);
+
+    /// Deliver messages to a connection.
+    fn send(&mut self, connection: ConnectionId, messages: Vec<Message>);
+}
+
+enum Session {
+    LoggingIn,
+    Playing { account: String, player: EntityId },
+}
+
+/// Routes output addressed to players to the connection each player is logged in on.
+struct Router<'a, C: Connections> {
+    connections: &'a mut C,
+    players: &'a HashMap<EntityId, ConnectionId>,
+}
+
+impl<C: Connections> OutputSink for Router<'_, C> {
+    fn send(&mut self, player: EntityId, messages: Vec<Message>) {
+        if let Some(connection) = self.players.get(&player) {
+            self.connections.send(*connection, messages);
+        }
+    }
+}
+
+struct Host<C: Connections> {
+    game: Game<'static, 'static>,
+    connections: C,
+    sessions: HashMap<ConnectionId, Session>,
+    players: HashMap<EntityId, ConnectionId>,
+}
+
+/// Run a game for the connections reporting to `events`, until every sender has been dropped.
+pub fn host<C: Connections>(events: mpsc::Receiver<Event<C::Client>>, connections: C) {
+    let mut host = Host {
+        game: Game::new(),
+        connections,
+        sessions: HashMap::new(),
+        players: HashMap::new(),
+    };
+
+    while let Ok(event) = events.recv() {
+        host.handle_event(event);
+        while let Ok(event) = events.try_recv() {
+            host.handle_event(event);
+        }
+
+        host.game.run_until_idle();
+        host.game.flush_output(&mut Router {
+            connections: &mut host.connections,
+            players: &host.players,
+        });
+    }
+}
+
+impl<C: Connections> Host<C> {
+    fn handle_event(&mut self, event: Event<C::Client>) {
+        match event {
+            Event::Connected(connection, client) => {
+                self.connections.add(connection, client);
+                self.sessions.insert(connection, Session::LoggingIn);
+                self.connections.send(connection, vec!["What is your name?".into()]);
+            }
+            Event::Line(connection, line) => match self.sessions.get(&connection) {
+                Some(Session::LoggingIn) => self.login(connection, &line),
+                Some(Session::Playing { player, .. }) => self.game.player_input(*player, &line),
+                None => {}
+            },
+            Event::Command(connection, command) => match self.sessions.get(&connection) {
+                Some(Session::LoggingIn) => {
+                    self.connections.send(connection, vec![Message::error("You need to log in first!")]);
+                }
+                Some(Session::Playing { player, .. }) => self.game.player_input(*player, &command),
+                None => {}
+            },
+            Event::Login(connection, name) => match self.sessions.get(&connection) {
+                Some(Session::LoggingIn) => self.login(connection, &name),
+                Some(Session::Playing { .. }) => {
+                    self.connections.send(connection, vec![Message::error("You are already logged in!")]);
+                }
+                None => {}
+            },
+            Event::Disconnected(connection) => {
+                if let Some(Session::Playing { account, player }) = self.sessions.remove(&connection) {
+                    self.game.logout(&account);
+                    self.players.remove(&player);
+                }
+                self.connections.remove(connection);
+            }
+        }
+    }
+
+    fn login(&mut self, connection: ConnectionId, name: &str) {
+        let name = name.trim();
+        let valid = !name.is_empty()
+            && name.chars().count() <= MAX_NAME_LENGTH
+            && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            self.connections.send(connection, vec![
+                Message::error(format!(
+                    "Names must be 1 to {} letters, numbers, dashes or underscores.",
+                    MAX_NAME_LENGTH,
+                )),
+                "What is your name?".into(),
+            ]);
+            return
+        }
+
+        // Names are case insensitive, so "Bob" and "bob" are the same character
+        let account = name.to_lowercase();
+        match self.game.login(&account) {
+            Ok(player) => {
+                self.connections.send(connection, vec![format!("Welcome, {}!", name).into()]);
+                self.sessions.insert(connection, Session::Playing { account, player });
+                self.players.insert(player, connection);
+                self.game.set_player_name(player, name);
+                self.game.player_input(player, "look");
+            }
+            Err(e) => {
+                self.connections.send(connection, vec![
+                    Message::error(e.to_string()),
+                    "What is your name?".into(),
+                ]);
+            }
+        }
+    }
+}
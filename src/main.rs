@@ -1,13 +1,19 @@
 pub mod cogs;
+pub mod console;
+pub mod discord;
 pub mod game;
+pub mod host;
+pub mod telnet;
 pub mod utils;
+pub mod websocket;
 
 
 fn main() {
-    println!("Creating the game object");
-    let mut g = game::Game::new();
-
-    loop {
-        g.tick()
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("discord") => discord::run(),
+        Some("telnet") => telnet::run(args.get(2).map(String::as_str)),
+        Some("websocket") => websocket::run(args.get(2).map(String::as_str)),
+        _ => console::run(),
     }
 }
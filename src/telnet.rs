@@ -0,0 +1,122 @@
+// Telnet front-end.
+//
+// A plain TCP line server in the classic MUD style, which works with any MUD client (or netcat).
+// Every connection logs in to its own character in a single shared game. Connections are read on their
+// own threads and funnelled into the thread which owns the game.
+// ----------------------------
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::game::Message;
+use crate::host::{self, ConnectionId, Connections};
+
+// Address to listen on when none is given.
+const DEFAULT_ADDRESS: &str = "127.0.0.1:4000";
+
+type Event = host::Event<TcpStream>;
+
+/// Writes game output back to each connection's socket.
+struct TcpConnections {
+    streams: HashMap<ConnectionId, TcpStream>,
+}
+
+impl Connections for TcpConnections {
+    type Client = TcpStream;
+
+    fn add(&mut self, connection: ConnectionId, stream: TcpStream) {
+        self.streams.insert(connection, stream);
+    }
+
+    fn remove(&mut self, connection: ConnectionId) {
+        self.streams.remove(&connection);
+    }
+
+    fn send(&mut self, connection: ConnectionId, messages: Vec<Message>) {
+        if let Some(stream) = self.streams.get_mut(&connection) {
+            let mut text = String::new();
+            for message in messages {
+                text.push_str(&message.to_string().replace('\n', "\r\n"));
+                text.push_str("\r\n");
+            }
+            if let Err(e) = stream.write_all(text.as_bytes()) {
+                log::warn!("Failed to write to connection {}: {:?}", connection, e);
+                self.streams.remove(&connection);
+            }
+        }
+    }
+}
+
+/// Run the telnet server on the given address, or the default one, until the process exits.
+pub fn run(address: Option<&str>) {
+    let address = address.unwrap_or(DEFAULT_ADDRESS);
+    let listener = TcpListener::bind(address)
+        .unwrap_or_else(|e| panic!("Failed to listen on {}: {:?}", address, e));
+    println!("Listening for telnet connections on {}", address);
+    serve(listener);
+}
+
+/// Host a game for every connection accepted by the listener.
+pub fn serve(listener: TcpListener) {
+    let (tx, rx) = mpsc::channel::<Event>();
+    thread::spawn(move || accept_connections(listener, tx));
+    host::host(rx, TcpConnections { streams: HashMap::new() });
+}
+
+fn accept_connections(listener: TcpListener, events: mpsc::Sender<Event>) {
+    let mut next_connection: ConnectionId = 0;
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("Failed to accept connection: {:?}", e);
+                continue
+            }
+        };
+        let writer = match stream.try_clone() {
+            Ok(writer) => writer,
+            Err(e) => {
+                log::warn!("Failed to set up connection: {:?}", e);
+                continue
+            }
+        };
+
+        let connection = next_connection;
+        next_connection += 1;
+        if events.send(Event::Connected(connection, writer)).is_err() {
+            return
+        }
+
+        let events = events.clone();
+        thread::spawn(move || read_lines(connection, stream, events));
+    }
+}
+
+fn read_lines(connection: ConnectionId, stream: TcpStream, events: mpsc::Sender<Event>) {
+    let mut reader = BufReader::new(stream);
+    let mut buffer: Vec<u8> = Vec::new();
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+
+        // Telnet clients send CRLF line endings and option negotiation bytes, neither of which
+        // mean anything to the game
+        let line: String = String::from_utf8_lossy(&buffer)
+            .chars()
+            .filter(|c| !c.is_control() && *c != char::REPLACEMENT_CHARACTER)
+            .collect();
+        let line = line.trim();
+        if line.is_empty() {
+            continue
+        }
+        if events.send(Event::Line(connection, line.to_string())).is_err() {
+            return
+        }
+    }
+    let _ = events.send(Event::Disconnected(connection));
+}
@@ -0,0 +1,168 @@
+// WebSocket front-end.
+//
+// Speaks a small JSON protocol so browser clients and bots don't have to scrape the text output.
+// Clients log in with `{"type": "login", "name": "bob"}` and then send commands as
+// `{"type": "command", "text": "get oboe"}`. Every message the game has for them arrives as its
+// own JSON object, tagged with a `type` such as `room`, `inventory`, `text` or `error`.
+//
+// Every connection logs in to its own character in a single shared game, like the telnet
+// front-end.
+// ----------------------------
+use std::collections::HashMap;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tungstenite::{Error, WebSocket};
+
+use crate::game::Message;
+use crate::host::{self, ConnectionId, Connections};
+
+// Address to listen on when none is given.
+const DEFAULT_ADDRESS: &str = "127.0.0.1:4001";
+
+// How long a connection waits for a message from its client before checking for game output.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+type Event = host::Event<mpsc::Sender<String>>;
+
+/// Messages accepted from clients.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Login { name: String },
+    Command { text: String },
+}
+
+/// Serializes game output and hands it to each connection's thread.
+struct SocketConnections {
+    clients: HashMap<ConnectionId, mpsc::Sender<String>>,
+}
+
+impl Connections for SocketConnections {
+    type Client = mpsc::Sender<String>;
+
+    fn add(&mut self, connection: ConnectionId, client: mpsc::Sender<String>) {
+        self.clients.insert(connection, client);
+    }
+
+    fn remove(&mut self, connection: ConnectionId) {
+        self.clients.remove(&connection);
+    }
+
+    fn send(&mut self, connection: ConnectionId, messages: Vec<Message>) {
+        if let Some(client) = self.clients.get(&connection) {
+            for message in messages {
+                let json = serde_json::to_string(&message).expect("Failed to serialize message");
+                if client.send(json).is_err() {
+                    self.clients.remove(&connection);
+                    return
+                }
+            }
+        }
+    }
+}
+
+/// Run the WebSocket server on the given address, or the default one, until the process exits.
+pub fn run(address: Option<&str>) {
+    let address = address.unwrap_or(DEFAULT_ADDRESS);
+    let listener = TcpListener::bind(address)
+        .unwrap_or_else(|e| panic!("Failed to listen on {}: {:?}", address, e));
+    println!("Listening for WebSocket connections on {}", address);
+    serve(listener);
+}
+
+/// Host a game for every connection accepted by the listener.
+pub fn serve(listener: TcpListener) {
+    let (tx, rx) = mpsc::channel::<Event>();
+    thread::spawn(move || accept_connections(listener, tx));
+    host::host(rx, SocketConnections { clients: HashMap::new() });
+}
+
+fn accept_connections(listener: TcpListener, events: mpsc::Sender<Event>) {
+    let mut next_connection: ConnectionId = 0;
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("Failed to accept connection: {:?}", e);
+                continue
+            }
+        };
+
+        let connection = next_connection;
+        next_connection += 1;
+        let events = events.clone();
+        thread::spawn(move || handle_connection(connection, stream, events));
+    }
+}
+
+/// Shuttle messages between a client and the game until either side goes away.
+fn handle_connection(connection: ConnectionId, stream: TcpStream, events: mpsc::Sender<Event>) {
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(e) => {
+            log::warn!("WebSocket handshake failed: {:?}", e);
+            return
+        }
+    };
+    if let Err(e) = socket.get_ref().set_read_timeout(Some(POLL_INTERVAL)) {
+        log::warn!("Failed to set up connection: {:?}", e);
+        return
+    }
+
+    let (tx, rx) = mpsc::channel::<String>();
+    if events.send(Event::Connected(connection, tx)).is_err() {
+        return
+    }
+
+    while forward_output(&mut socket, &rx) {
+        let text = match socket.read_message() {
+            Ok(tungstenite::Message::Text(text)) => text,
+            Ok(tungstenite::Message::Close(_)) => break,
+            Ok(_) => continue,
+            Err(Error::Io(e)) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => continue,
+            Err(_) => break,
+        };
+
+        match serde_json::from_str::<ClientMessage>(&text) {
+            Ok(ClientMessage::Login { name }) => {
+                if events.send(Event::Login(connection, name)).is_err() {
+                    break
+                }
+            }
+            Ok(ClientMessage::Command { text }) => {
+                if events.send(Event::Command(connection, text)).is_err() {
+                    break
+                }
+            }
+            Err(e) => {
+                let error = Message::error(format!("Invalid message: {}", e));
+                let json = serde_json::to_string(&error).expect("Failed to serialize message");
+                if socket.write_message(tungstenite::Message::Text(json)).is_err() {
+                    break
+                }
+            }
+        }
+    }
+
+    let _ = events.send(Event::Disconnected(connection));
+}
+
+/// Write any output waiting for the client, returning whether the connection is still usable.
+fn forward_output(socket: &mut WebSocket<TcpStream>, output: &mpsc::Receiver<String>) -> bool {
+    loop {
+        match output.try_recv() {
+            Ok(json) => {
+                if socket.write_message(tungstenite::Message::Text(json)).is_err() {
+                    return false
+                }
+            }
+            Err(mpsc::TryRecvError::Empty) => return true,
+            Err(mpsc::TryRecvError::Disconnected) => return false,
+        }
+    }
+}